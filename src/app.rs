@@ -2,22 +2,124 @@ use anyhow::Result;
 use eframe::egui::{
     self, CentralPanel, CollapsingHeader, ScrollArea, SidePanel, TopBottomPanel, ViewportCommand,
 };
-use std::path::Path;
+use std::{
+    ops::Range,
+    path::{Path, PathBuf},
+};
 
 // Used for texture
 use pigment64::ImageType;
 use strum::IntoEnumIterator;
 
 use crate::{
-    bin_handler::BinFile,
+    bin_handler::{BinFile, MMAP_THRESHOLD},
+    diff::{
+        diff_ranges_with_progress, find_alignment_with_progress, Comparison, MAX_ALIGN_SHIFT,
+        MERGE_GAP,
+    },
+    display_list::{Microcode, TextureDescriptor, TileDescriptor},
+    duplicates::{distinct_regions, file_regions, group_duplicates_with_progress, TextureData},
+    export::{decode_texture, encode_png, ModelFormat},
+    hex_view::{hex_dump, visible_start},
+    import::{build_display_list, load_model, load_png, BuildOptions, ImportedModel},
+    jobs::Jobs,
+    minimap::{block_size, classify_with_progress, ByteClass, Minimap},
+    model_view::ModelView,
     motex_options::{options_window, Appearance},
-    texview::TexView,
+    onion::{OnionImage, OnionSkin, OverlayPixels, OverlaySource},
+    savestate::is_savestate_path,
+    search::{find_pattern_with_progress, search_window, Search, SearchRequest, CHUNK_SIZE},
+    segments::{
+        address_link, parse_address, segment_window, AddressSpace, SegmentSource, SegmentTable,
+    },
+    texture_search::{find_texture_with_progress, TextureHit},
+    texview::{bpp_from_image_type, image_byte_size, ByteSwap, RawPixel, TexView},
+    vtx_view::VtxView,
+    watcher::{FileWatcher, POLL_INTERVAL},
 };
 
 #[derive(Default)]
 pub struct ViewState {
     show_about: bool,
     show_options: bool,
+    show_segments: bool,
+    show_import: bool,
+    show_compare: bool,
+    show_find_png: bool,
+    show_duplicates: bool,
+    show_search: bool,
+}
+
+/// Settings and results of the duplicate texture analysis.
+struct DuplicateSearch {
+    /// Whether the textures of the loaded display list are compared.
+    display_list: bool,
+    /// Whether the whole file is compared, split into textures of the
+    /// texture view's format and size.
+    whole_file: bool,
+    /// Whether textures that only match after decoding are grouped too.
+    near: bool,
+    /// The allowed difference per decoded channel for near matches.
+    tolerance: u8,
+    /// The number of textures compared by the last analysis.
+    compared: usize,
+    /// Groups of identical textures.
+    groups: Vec<Vec<TextureDescriptor>>,
+}
+
+impl Default for DuplicateSearch {
+    fn default() -> Self {
+        Self {
+            display_list: true,
+            whole_file: false,
+            near: false,
+            tolerance: 0,
+            compared: 0,
+            groups: vec![],
+        }
+    }
+}
+
+/// What a background job hands back to the UI.
+enum JobOutput {
+    Opened(BinFile),
+    SearchHits(Vec<usize>),
+    PngHits(Vec<TextureHit>),
+    DiffRanges(Vec<Range<usize>>),
+    Alignment(i64),
+    Duplicates {
+        compared: usize,
+        groups: Vec<Vec<TextureDescriptor>>,
+    },
+    ByteClasses {
+        generation: u64,
+        block_size: usize,
+        classes: Vec<ByteClass>,
+    },
+    Exported,
+}
+
+/// A PNG being searched for in the open file.
+struct PngSearch {
+    path: PathBuf,
+    width: usize,
+    height: usize,
+    rgba: Vec<u8>,
+    /// The allowed difference per channel, in quantization steps.
+    tolerance: u8,
+    hits: Vec<TextureHit>,
+}
+
+/// What the central panel displays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CentralMode {
+    /// The texture decoded at the current file position.
+    #[default]
+    Texture,
+    /// The mesh produced by running a display list.
+    Model,
+    /// The data at the current file position read as `Vtx` structs.
+    Vertices,
 }
 
 /// The Motex Application.
@@ -29,12 +131,66 @@ pub struct Motex {
     file: BinFile,
     /// The current position into the file.
     file_pos: usize,
+    /// Segment table used to resolve segmented addresses.
+    segments: SegmentTable,
+    /// The file `SegmentSource::OpenFile` segments refer to, while `file`
+    /// holds an external segment source that an address led to.
+    segment_base: Option<BinFile>,
+    /// Text of the "Go to" address field.
+    goto_text: String,
     // Middle panel stuff
+    central_mode: CentralMode,
     sample32_tex: TexView,
+    /// Segmented address of the palette used for CI formats, if one is set.
+    palette_addr: Option<u32>,
+    model_view: ModelView,
+    /// Text of the display list address field.
+    dl_text: String,
+    vtx_view: VtxView,
+    /// The model being imported, if any.
+    imported_model: Option<ImportedModel>,
+    /// Settings for generating a display list from `imported_model`.
+    build_options: BuildOptions,
+    /// The file the open one is compared against, if any.
+    comparison: Option<Comparison>,
+    /// Whether bytes that differ from the compared file are tinted.
+    tint_differences: bool,
+    /// Whether `comparison` needs to be diffed again, after the open file
+    /// or the shift changed.
+    diff_outdated: bool,
+    /// The job diffing `comparison`, if any.
+    diff_job: Option<u64>,
+    /// The job looking for the shift that best aligns `comparison`, if any.
+    align_job: Option<u64>,
+    /// The job grouping duplicate textures, if any.
+    duplicates_job: Option<u64>,
+    /// A second image drawn over the texture for comparison.
+    onion: OnionSkin,
+    /// The PNG last searched for, with its hits.
+    png_search: Option<PngSearch>,
+    duplicates: DuplicateSearch,
+    /// The byte pattern search and its hits.
+    search: Search,
+    /// Scans running in the background.
+    jobs: Jobs<JobOutput>,
+    /// The job running `search`, if any.
+    search_job: Option<u64>,
+    /// The job running `png_search`, if any.
+    png_job: Option<u64>,
+    /// The job reading a file the user opened, if any.
+    open_job: Option<u64>,
+    /// The job writing an exported model, if any.
+    export_job: Option<u64>,
+    /// Reloads the open file when it changes on disk.
+    watcher: FileWatcher,
+    /// Bytes to highlight in the hex panel.
+    hex_highlight: Option<Range<usize>>,
     // Preview panel stuff
     preview_tex: TexView,
-    /// The color that is currently being hovered over.
-    hover_color: Option<egui::Color32>,
+    /// Byte class map of the whole file, drawn beside the preview.
+    minimap: Minimap,
+    /// The job classifying the file for `minimap`, if any.
+    minimap_job: Option<u64>,
     /// View state for the application.
     view_state: ViewState,
     /// Error message to display.
@@ -49,6 +205,7 @@ impl Motex {
         // Initialize both texture views with sensible default dimensions
         sample32_tex.width = 32;
         sample32_tex.height = 32;
+        sample32_tex.overlays.crosshair = true;
         preview_tex.width = 64;
         preview_tex.height = 64;
 
@@ -56,24 +213,445 @@ impl Motex {
             format: ImageType::I8,
             file: BinFile::default(),
             file_pos: 0,
+            segments: SegmentTable::default(),
+            segment_base: None,
+            goto_text: String::new(),
+            central_mode: CentralMode::default(),
             sample32_tex,
-            hover_color: Some(egui::Color32::from_rgba_premultiplied(0, 0, 0, 0)),
+            palette_addr: None,
+            model_view: ModelView::default(),
+            dl_text: String::new(),
+            vtx_view: VtxView::default(),
+            imported_model: None,
+            build_options: BuildOptions::default(),
+            comparison: None,
+            tint_differences: true,
+            diff_outdated: false,
+            onion: OnionSkin::default(),
+            png_search: None,
+            duplicates: DuplicateSearch::default(),
+            search: Search::default(),
+            jobs: Jobs::default(),
+            search_job: None,
+            png_job: None,
+            open_job: None,
+            export_job: None,
+            diff_job: None,
+            align_job: None,
+            duplicates_job: None,
+            watcher: FileWatcher::default(),
+            hex_highlight: None,
             preview_tex,
+            minimap: Minimap::default(),
+            minimap_job: None,
             appearance: Appearance::default(),
             view_state: ViewState::default(),
             error_message: None,
         }
     }
 
-    /// Returns data from the currently open file.
+    /// Opens a file in the background, replacing the open one once it is
+    /// read.
+    ///
+    /// Savestates are opened as their RDRAM image, which takes unpacking.
+    /// Large files are memory-mapped if the user agrees.
     ///
     /// ### Arguments
+    /// * `ctx` - The egui context, repainted when the file is read.
     /// * `path` - The path to the file to open.
-    pub fn open_file(&mut self, path: &Path) -> Result<()> {
-        self.file = BinFile::from_path(path)?;
+    pub fn open_file(&mut self, ctx: &egui::Context, path: &Path) -> Result<()> {
+        let large = std::fs::metadata(path)?.len() >= MMAP_THRESHOLD;
+        let map = large && !is_savestate_path(path) && confirm_map(path);
+        if let Some(id) = self.open_job.take() {
+            self.jobs.cancel(id);
+        }
+        let path = path.to_path_buf();
+        let name = format!("Opening {}", path.display());
+        self.open_job = Some(self.jobs.spawn(Some(ctx), &name, move |job| {
+            let file = if map {
+                BinFile::map(&path)?
+            } else {
+                BinFile::open(&path)?
+            };
+            job.check_cancelled()?;
+            Ok(JobOutput::Opened(file))
+        }));
+        Ok(())
+    }
+
+    /// Replaces the open file with one that was just read, dropping what
+    /// belonged to the old one.
+    ///
+    /// ### Arguments
+    /// * `file` - The newly opened file.
+    fn set_file(&mut self, file: BinFile) {
+        // A file opened another way wins over one still being read
+        if let Some(id) = self.open_job.take() {
+            self.jobs.cancel(id);
+        }
+        self.file = file;
+        self.segment_base = None;
+        self.error_message = None;
+        self.watch_file();
+        self.cancel_scans();
+        self.search.hits.clear();
+        self.search.range_end = self.file.data.len();
+        self.diff_outdated = self.comparison.is_some();
+        self.sync_highlights();
+    }
+
+    /// Watches the open file for changes, unless it is memory-mapped: a
+    /// rebuild that truncates a mapped file crashes motex, and reloading
+    /// cannot happen before that.
+    fn watch_file(&mut self) {
+        let file = self.segment_base.as_ref().unwrap_or(&self.file);
+        if file.is_mapped() {
+            self.watcher.unwatch();
+        } else {
+            self.watcher.watch(&file.path);
+        }
+    }
+
+    /// Cancels the scans of the open file, whose results would not fit a
+    /// newly loaded one.
+    fn cancel_scans(&mut self) {
+        for id in [self.search_job.take(), self.png_job.take()]
+            .into_iter()
+            .flatten()
+        {
+            self.jobs.cancel(id);
+        }
+    }
+
+    /// Starts a background search for the pattern in `search`.
+    ///
+    /// ### Arguments
+    /// * `ctx` - The egui context, repainted as the search progresses.
+    fn start_search(&mut self, ctx: &egui::Context) {
+        if let Some(id) = self.search_job.take() {
+            self.jobs.cancel(id);
+        }
+        let Some((pattern, range)) = self.search.prepare(self.file.data.len()) else {
+            return;
+        };
+        let data = self.file.data.clone();
+        let alignment = self.search.kind.alignment();
+        let name = format!("Searching for \"{}\"", self.search.text);
+        self.search_job = Some(self.jobs.spawn(Some(ctx), &name, move |job| {
+            let hits = find_pattern_with_progress(
+                &data,
+                &pattern,
+                range,
+                alignment,
+                CHUNK_SIZE,
+                |fraction| job.tick(fraction),
+            );
+            job.check_cancelled()?;
+            Ok(JobOutput::SearchHits(hits))
+        }));
+    }
+
+    /// Starts diffing the open file against the compared one in the
+    /// background, replacing any diff still running.
+    ///
+    /// ### Arguments
+    /// * `ctx` - The egui context, repainted as the diff progresses.
+    fn start_diff(&mut self, ctx: &egui::Context) {
+        self.diff_outdated = false;
+        if let Some(id) = self.diff_job.take() {
+            self.jobs.cancel(id);
+        }
+        let Some(comparison) = &self.comparison else {
+            return;
+        };
+        let data = self.file.data.clone();
+        let other = comparison.other.data.clone();
+        let shift = comparison.shift;
+        self.diff_job = Some(self.jobs.spawn(Some(ctx), "Comparing files", move |job| {
+            let ranges = diff_ranges_with_progress(
+                &data,
+                &other,
+                shift,
+                MERGE_GAP,
+                CHUNK_SIZE,
+                |fraction| job.tick(fraction),
+            );
+            job.check_cancelled()?;
+            Ok(JobOutput::DiffRanges(ranges))
+        }));
+    }
+
+    /// Classifies the shown file for the minimap in the background when it
+    /// is not the one last classified.
+    ///
+    /// ### Arguments
+    /// * `ctx` - The egui context, repainted as the classification progresses.
+    fn update_minimap(&mut self, ctx: &egui::Context) {
+        if !self.minimap.request(self.file.generation) {
+            return;
+        }
+        if let Some(id) = self.minimap_job.take() {
+            self.jobs.cancel(id);
+        }
+        let data = self.file.data.clone();
+        let generation = self.file.generation;
+        self.minimap_job = Some(self.jobs.spawn(Some(ctx), "Building minimap", move |job| {
+            let block_size = block_size(data.len());
+            let classes = classify_with_progress(&data, block_size, |fraction| job.tick(fraction));
+            job.check_cancelled()?;
+            Ok(JobOutput::ByteClasses {
+                generation,
+                block_size,
+                classes,
+            })
+        }));
+    }
+
+    /// Starts looking for the shift that best aligns the compared file in
+    /// the background, replacing any search still running.
+    ///
+    /// ### Arguments
+    /// * `ctx` - The egui context, repainted as the search progresses.
+    fn start_alignment(&mut self, ctx: &egui::Context) {
+        if let Some(id) = self.align_job.take() {
+            self.jobs.cancel(id);
+        }
+        let Some(comparison) = &self.comparison else {
+            return;
+        };
+        let data = self.file.data.clone();
+        let other = comparison.other.data.clone();
+        self.align_job = Some(self.jobs.spawn(Some(ctx), "Aligning files", move |job| {
+            let shift = find_alignment_with_progress(&data, &other, MAX_ALIGN_SHIFT, |fraction| {
+                job.tick(fraction)
+            });
+            job.check_cancelled()?;
+            Ok(JobOutput::Alignment(shift))
+        }));
+    }
+
+    /// Hands the results of finished jobs to the features that started them.
+    fn poll_jobs(&mut self) {
+        for finished in self.jobs.poll() {
+            let current = [
+                self.open_job,
+                self.search_job,
+                self.png_job,
+                self.diff_job,
+                self.align_job,
+                self.duplicates_job,
+                self.minimap_job,
+                self.export_job,
+            ]
+            .contains(&Some(finished.id));
+            if !current {
+                // Superseded by a newer job of the same kind
+                continue;
+            }
+            match finished.result {
+                Ok(JobOutput::Opened(file)) => {
+                    self.open_job = None;
+                    self.set_file(file);
+                }
+                Ok(JobOutput::SearchHits(hits)) => {
+                    self.search_job = None;
+                    self.search.hits = hits;
+                }
+                Ok(JobOutput::PngHits(hits)) => {
+                    self.png_job = None;
+                    if let Some(search) = &mut self.png_search {
+                        search.hits = hits;
+                    }
+                }
+                Ok(JobOutput::DiffRanges(ranges)) => {
+                    self.diff_job = None;
+                    if let Some(comparison) = &mut self.comparison {
+                        comparison.ranges = ranges;
+                    }
+                    self.sync_highlights();
+                }
+                Ok(JobOutput::Alignment(shift)) => {
+                    self.align_job = None;
+                    if let Some(comparison) = &mut self.comparison {
+                        comparison.shift = shift;
+                        self.diff_outdated = true;
+                    }
+                }
+                Ok(JobOutput::Duplicates { compared, groups }) => {
+                    self.duplicates_job = None;
+                    self.duplicates.compared = compared;
+                    self.duplicates.groups = groups;
+                }
+                Ok(JobOutput::ByteClasses {
+                    generation,
+                    block_size,
+                    classes,
+                }) => {
+                    self.minimap_job = None;
+                    self.minimap.set_classes(generation, block_size, classes);
+                }
+                Ok(JobOutput::Exported) => {
+                    self.export_job = None;
+                }
+                Err(e) => {
+                    self.open_job = self.open_job.filter(|&id| id != finished.id);
+                    self.align_job = self.align_job.filter(|&id| id != finished.id);
+                    self.minimap_job = self.minimap_job.filter(|&id| id != finished.id);
+                    self.export_job = self.export_job.filter(|&id| id != finished.id);
+                    self.duplicates_job = self.duplicates_job.filter(|&id| id != finished.id);
+                    self.search_job = self.search_job.filter(|&id| id != finished.id);
+                    self.png_job = self.png_job.filter(|&id| id != finished.id);
+                    self.diff_job = self.diff_job.filter(|&id| id != finished.id);
+                    eprintln!("{} failed: {}", finished.name, e);
+                    self.error_message = Some(format!("{} failed: {}", finished.name, e));
+                }
+            }
+        }
+    }
+
+    /// Shows a progress bar and Cancel button for each running job.
+    ///
+    /// ### Args
+    /// * `ctx` - egui context
+    fn render_jobs_panel(&mut self, ctx: &egui::Context) {
+        if self.jobs.is_empty() {
+            return;
+        }
+        TopBottomPanel::bottom("jobs_panel").show(ctx, |ui| {
+            self.jobs.ui(ui);
+        });
+    }
+
+    /// Tints the differences from the compared file in both texture views,
+    /// or clears the tint.
+    fn sync_highlights(&mut self) {
+        let highlights = match &self.comparison {
+            Some(comparison) if self.tint_differences => comparison.ranges.clone(),
+            _ => vec![],
+        };
+        self.sample32_tex.highlights = highlights.clone();
+        self.preview_tex.highlights = highlights;
+    }
+
+    /// Re-reads the open file after it changed on disk, keeping the
+    /// position, base address, view settings and searches.
+    ///
+    /// Only what depends on the bytes is redone: searches, the diff, the
+    /// duplicate analysis and the loaded model are run again on the new
+    /// contents.
+    ///
+    /// ### Arguments
+    /// * `ctx` - The egui context, repainted as the re-run jobs progress.
+    fn reload_file(&mut self, ctx: &egui::Context) {
+        let base = self.segment_base.as_mut().unwrap_or(&mut self.file);
+        // Only files that were read into memory are watched, and `open`
+        // keeps them that way even if they grew past the mapping threshold
+        let mut file = match BinFile::open(&base.path) {
+            Ok(file) => file,
+            Err(e) => {
+                eprintln!("Failed to reload file: {}", e);
+                self.error_message = Some(format!("Failed to reload file: {}", e));
+                return;
+            }
+        };
+        file.base_address = base.base_address;
+        *base = file;
+        self.watcher.last_reload = Some(std::time::Instant::now());
+        self.watch_file();
+
+        let len = self.file.data.len();
+        self.file_pos = self.file_pos.min(len);
+        self.search.range_end = self.search.range_end.min(len);
+        if self.search_job.is_some() || !self.search.hits.is_empty() {
+            self.start_search(ctx);
+        }
+        self.search_png(ctx);
+        self.diff_outdated = self.comparison.is_some();
+        if self.duplicates_job.is_some() || !self.duplicates.groups.is_empty() {
+            self.find_duplicates(ctx);
+        }
+        if self.model_view.mesh().is_some() {
+            let (yaw, pitch, distance) = (
+                self.model_view.yaw,
+                self.model_view.pitch,
+                self.model_view.distance,
+            );
+            self.load_display_list(ctx);
+            self.model_view.yaw = yaw;
+            self.model_view.pitch = pitch;
+            self.model_view.distance = distance;
+        }
+    }
+
+    /// Moves the view to the bytes a segmented address refers to.
+    ///
+    /// Addresses inside the file's virtual address range are used directly.
+    /// If the address lives in an external file, that file is shown in place
+    /// of the open one, which stays the source of `OpenFile` segments until
+    /// an address leads back to it.
+    ///
+    /// ### Arguments
+    /// * `addr` - The segmented address to navigate to.
+    pub fn navigate_to(&mut self, addr: u32) -> Result<()> {
+        let base = self.segment_base.as_ref().unwrap_or(&self.file);
+        if let Some(offset) = base.offset_of(addr) {
+            self.show_segment_base();
+            self.file_pos = offset;
+            return Ok(());
+        }
+
+        let Some(resolved) = self.segments.resolve(addr) else {
+            anyhow::bail!("Address 0x{:08X} is in an unmapped segment", addr);
+        };
+        let offset = resolved.offset;
+
+        match resolved.source {
+            SegmentSource::OpenFile => self.show_segment_base(),
+            SegmentSource::External(path) => {
+                if *path != self.file.path {
+                    let external = BinFile::read(path)?;
+                    let shown = std::mem::replace(&mut self.file, external);
+                    self.segment_base.get_or_insert(shown);
+                }
+            }
+        }
+
+        if offset >= self.file.data.len() {
+            anyhow::bail!(
+                "Address 0x{:08X} resolves past the end of the file (0x{:X})",
+                addr,
+                offset
+            );
+        }
+        self.file_pos = offset;
         Ok(())
     }
 
+    /// The segmented address of the current position in the open file.
+    ///
+    /// Returns `None` while an external segment source is shown, since
+    /// `SegmentTable::to_segmented` only knows the open file.
+    fn segmented_pos(&self) -> Option<u32> {
+        match self.segment_base {
+            Some(_) => None,
+            None => self.segments.to_segmented(self.file_pos),
+        }
+    }
+
+    /// Shows the open file again after an external segment source.
+    fn show_segment_base(&mut self) {
+        if let Some(base) = self.segment_base.take() {
+            self.file = base;
+        }
+    }
+
+    fn navigate_or_report(&mut self, addr: u32) {
+        if let Err(e) = self.navigate_to(addr) {
+            eprintln!("Failed to navigate: {}", e);
+            self.error_message = Some(e.to_string());
+        }
+    }
+
     fn pre_update(&mut self, ctx: &egui::Context) {
         self.appearance.apply_appearance(ctx);
     }
@@ -113,6 +691,18 @@ impl Motex {
                     }
                 }
             });
+
+        ui.horizontal(|ui| {
+            ui.label("Byte swap:");
+            egui::ComboBox::from_id_salt("byte_swap")
+                .selected_text(self.sample32_tex.swap.name())
+                .show_ui(ui, |ui| {
+                    for swap in ByteSwap::ALL {
+                        ui.selectable_value(&mut self.sample32_tex.swap, swap, swap.name());
+                    }
+                });
+        });
+        self.preview_tex.swap = self.sample32_tex.swap;
     }
 
     fn render_color_info(&self, ui: &mut egui::Ui) {
@@ -134,6 +724,62 @@ impl Motex {
         } else {
             ui.label("Hover over the image to see color info");
         }
+
+        if let Some(raw) = self
+            .sample32_tex
+            .hovered_raw_pixel(&self.file.data, self.file_pos)
+        {
+            ui.separator();
+            self.render_raw_pixel(ui, &raw);
+        }
+    }
+
+    /// Shows where the hovered pixel is stored and its encoded value.
+    /// ### Arguments
+    /// * `ui` - The egui ui to draw into.
+    /// * `raw` - The hovered pixel.
+    fn render_raw_pixel(&self, ui: &mut egui::Ui, raw: &RawPixel) {
+        ui.label(format!("Pixel: {}, {}", raw.x, raw.y));
+        ui.monospace(format!("Offset: 0x{:08X}", raw.offset));
+        if self.file.base_address != 0 {
+            ui.monospace(format!(
+                "Address: 0x{:08X}",
+                self.file.address_of(raw.offset)
+            ));
+        }
+        if raw.bits < 8 {
+            ui.label(format!("Bits: {}-{}", raw.bit + raw.bits - 1, raw.bit));
+        }
+
+        let digits = (raw.bits as usize).div_ceil(4);
+        let value = format!("0x{:0digits$X}", raw.value);
+        match self.format {
+            ImageType::Rgba16 => {
+                ui.monospace(format!("RGBA5551: {}", value));
+                ui.monospace(format!(
+                    "R{} G{} B{} A{}",
+                    raw.value >> 11,
+                    (raw.value >> 6) & 0x1F,
+                    (raw.value >> 1) & 0x1F,
+                    raw.value & 1
+                ));
+            }
+            ImageType::Ci4 | ImageType::Ci8 => {
+                ui.monospace(format!("Index: {} ({})", raw.value, value));
+                match self.palette_addr {
+                    Some(addr) => {
+                        let entry = addr.wrapping_add(raw.value * 2);
+                        ui.monospace(format!("Entry: 0x{:08X}", entry));
+                    }
+                    None => {
+                        ui.label("No palette set");
+                    }
+                }
+            }
+            format => {
+                ui.monospace(format!("{:?}: {}", format, value));
+            }
+        }
     }
 
     fn update_image_format(&mut self, format: ImageType) {
@@ -153,29 +799,625 @@ impl Motex {
     }
 
     fn render_central_panel_content(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.central_mode, CentralMode::Texture, "Texture");
+            ui.selectable_value(&mut self.central_mode, CentralMode::Model, "Model");
+            ui.selectable_value(&mut self.central_mode, CentralMode::Vertices, "Vtx");
+        });
+        ui.separator();
+
+        match self.central_mode {
+            CentralMode::Texture => self.render_texture_view(ui, ctx),
+            CentralMode::Model => self.render_model_view(ui, ctx),
+            CentralMode::Vertices => self.vtx_view.draw(ui, &self.file.data, self.file_pos),
+        }
+    }
+
+    fn render_texture_view(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
         // Add zoom controls
         ui.horizontal(|ui| {
+            let tex = &mut self.sample32_tex;
             ui.label("Zoom:");
-            if ui.button("-").clicked() && self.sample32_tex.zoom > 0.5 {
-                self.sample32_tex.zoom /= 2.0;
+            if ui.button("-").clicked() {
+                tex.zoom_by(0.5);
             }
-            ui.label(format!("{:.2}x", self.sample32_tex.zoom));
-            if ui.button("+").clicked() && self.sample32_tex.zoom < 8.0 {
-                self.sample32_tex.zoom *= 2.0;
+            ui.label(format!("{:.2}x", tex.zoom));
+            if ui.button("+").clicked() {
+                tex.zoom_by(2.0);
+            }
+            if ui.button("Fit").clicked() {
+                tex.fit();
             }
             if ui.button("Reset").clicked() {
-                self.sample32_tex.zoom = 1.0;
+                tex.zoom = 1.0;
+                tex.pan = egui::Vec2::ZERO;
+            }
+            ui.separator();
+            ui.label("Size:");
+            ui.add(egui::DragValue::new(&mut self.sample32_tex.width).range(1..=1024));
+            ui.label("x");
+            ui.add(egui::DragValue::new(&mut self.sample32_tex.height).range(1..=1024));
+
+            ui.separator();
+            ui.menu_button("Overlays", |ui| {
+                let overlays = &mut self.sample32_tex.overlays;
+                ui.checkbox(&mut overlays.pixel_grid, "Pixel grid");
+                ui.checkbox(&mut overlays.tmem_words, "TMEM words");
+                ui.checkbox(&mut overlays.crosshair, "Crosshair");
+                ui.horizontal(|ui| {
+                    ui.label("Blocks:");
+                    ui.selectable_value(&mut overlays.block_size, 0, "Off");
+                    ui.selectable_value(&mut overlays.block_size, 4, "4x4");
+                    ui.selectable_value(&mut overlays.block_size, 8, "8x8");
+                });
+            });
+            ui.checkbox(&mut self.sample32_tex.tiled, "Tiled");
+            let mut framebuffer = self.sample32_tex.stride.is_some();
+            if ui.checkbox(&mut framebuffer, "Framebuffer").changed() {
+                self.sample32_tex.stride = framebuffer.then_some(self.sample32_tex.width);
+            }
+
+            ui.separator();
+            if ui
+                .add_enabled(!self.file.data.is_empty(), egui::Button::new("Export PNG"))
+                .clicked()
+            {
+                self.export_texture();
+            }
+
+            if let Some(addr) = self.palette_addr {
+                ui.separator();
+                ui.label("Palette:");
+                if address_link(ui, &self.segments, addr) {
+                    self.navigate_or_report(addr);
+                }
+                if ui.button("Clear").clicked() {
+                    self.set_palette(None, None);
+                }
             }
         });
 
+        if self.sample32_tex.tiled {
+            self.render_tile_controls(ui);
+        }
+        if self.sample32_tex.stride.is_some() {
+            self.render_framebuffer_controls(ui);
+        }
+
         // Draw the texture
         if self.file.data.is_empty() {
             ui.centered_and_justified(|ui| {
                 ui.label("No image loaded. Please open a file.");
             });
         } else {
-            self.sample32_tex
-                .draw(&self.file.data, self.file_pos, ui, ctx);
+            self.sample32_tex.onion = self.onion_image();
+            self.sample32_tex.draw_viewport(
+                &self.file.data,
+                self.file.generation,
+                self.file_pos,
+                ui,
+                ctx,
+            );
+            self.onion.differing_pixels = self.sample32_tex.onion_differing;
+        }
+    }
+
+    /// Decodes the image the onion skin draws over the texture, if enabled.
+    ///
+    /// Decoded overlays are cached by the texture view, so this is cheap
+    /// while the source and settings stay the same.
+    fn onion_image(&mut self) -> Option<OnionImage> {
+        if !self.onion.enabled {
+            return None;
+        }
+        let pixels = match &self.onion.source {
+            OverlaySource::Offset => self.sample32_tex.render_overlay(
+                &self.file.data,
+                self.file.generation,
+                self.onion.offset,
+            ),
+            OverlaySource::ComparedFile => {
+                let comparison = self.comparison.as_ref()?;
+                let offset = (self.file_pos as i64 + comparison.shift).max(0) as usize;
+                self.sample32_tex.render_overlay(
+                    &comparison.other.data,
+                    comparison.other.generation,
+                    offset,
+                )
+            }
+            OverlaySource::Png { pixels, .. } => pixels.clone(),
+        };
+        Some(OnionImage {
+            pixels,
+            mode: self.onion.mode,
+            opacity: self.onion.opacity,
+        })
+    }
+
+    /// Renders the onion skin source and blend controls.
+    /// ### Arguments
+    /// * `ui` - The egui ui to draw into.
+    fn render_onion_controls(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.onion.enabled, "Enabled");
+
+        let source_name = match &self.onion.source {
+            OverlaySource::Offset => "Offset",
+            OverlaySource::ComparedFile => "Compared file",
+            OverlaySource::Png { .. } => "PNG",
+        };
+        let mut load_png = false;
+        egui::ComboBox::from_id_salt("onion_source")
+            .selected_text(source_name)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.onion.source, OverlaySource::Offset, "Offset");
+                ui.add_enabled_ui(self.comparison.is_some(), |ui| {
+                    ui.selectable_value(
+                        &mut self.onion.source,
+                        OverlaySource::ComparedFile,
+                        "Compared file",
+                    );
+                });
+                load_png = ui.selectable_label(false, "PNG...").clicked();
+            });
+
+        match &self.onion.source {
+            OverlaySource::Offset => {
+                ui.horizontal(|ui| {
+                    ui.label("Offset:");
+                    ui.add(
+                        egui::DragValue::new(&mut self.onion.offset).hexadecimal(8, false, true),
+                    );
+                    if ui.button("Here").clicked() {
+                        self.onion.offset = self.file_pos;
+                    }
+                });
+            }
+            OverlaySource::ComparedFile => {}
+            OverlaySource::Png { height, pixels } => {
+                ui.label(format!("{}x{}", pixels.width, height));
+            }
+        }
+
+        self.onion.blend_ui(ui);
+
+        if load_png {
+            self.load_onion_png();
+        }
+    }
+
+    fn load_onion_png(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("PNG", &["png"])
+            .pick_file()
+        else {
+            return;
+        };
+
+        match load_png(&path) {
+            Ok((width, height, rgba)) => {
+                self.onion.source = OverlaySource::Png {
+                    height,
+                    pixels: OverlayPixels::new(width, rgba),
+                };
+                self.onion.enabled = true;
+            }
+            Err(e) => {
+                eprintln!("Failed to load PNG: {}", e);
+                self.error_message = Some(format!("Failed to load PNG: {}", e));
+            }
+        }
+    }
+
+    /// Asks for a path and writes the texture view as a PNG, exactly as
+    /// shown, including the combiner and tiling.
+    fn export_texture(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("PNG", &["png"])
+            .set_file_name(format!("tex_{:08X}.png", self.file_pos))
+            .save_file()
+        else {
+            return;
+        };
+
+        let (width, height, rgba) = self.sample32_tex.render(&self.file.data, self.file_pos);
+        let result =
+            encode_png(width, height, &rgba).and_then(|png| Ok(std::fs::write(&path, png)?));
+        if let Err(e) = result {
+            eprintln!("Failed to export texture: {}", e);
+            self.error_message = Some(format!("Failed to export texture: {}", e));
+        }
+    }
+
+    /// Renders the row stride and common framebuffer layouts.
+    fn render_framebuffer_controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if let Some(stride) = &mut self.sample32_tex.stride {
+                ui.label("Stride:");
+                ui.add(egui::DragValue::new(stride).range(1..=4096))
+                    .on_hover_text("Distance between rows, in pixels");
+            }
+
+            for format in [ImageType::Rgba16, ImageType::Rgba32] {
+                if ui
+                    .selectable_label(self.format == format, format!("{:?}", format))
+                    .clicked()
+                {
+                    self.update_image_format(format);
+                }
+            }
+
+            ui.separator();
+            for (width, height, stride) in [(320, 240, 320), (640, 480, 640), (320, 240, 640)] {
+                let label = if stride == width {
+                    format!("{}x{}", width, height)
+                } else {
+                    format!("{}x{} in {}", width, height, stride)
+                };
+                if ui.button(label).clicked() {
+                    self.sample32_tex.width = width;
+                    self.sample32_tex.height = height;
+                    self.sample32_tex.stride = Some(stride);
+                }
+            }
+        });
+    }
+
+    /// Renders the tile descriptor settings used by the tiled preview.
+    fn render_tile_controls(&mut self, ui: &mut egui::Ui) {
+        let tex = &mut self.sample32_tex;
+        ui.horizontal(|ui| {
+            ui.label("Repeats:");
+            ui.add(egui::DragValue::new(&mut tex.tile_repeats).range(1..=9));
+
+            let tile = &mut tex.tile;
+            for (axis, cm, mask, shift) in [
+                ("S", &mut tile.cms, &mut tile.masks, &mut tile.shifts),
+                ("T", &mut tile.cmt, &mut tile.maskt, &mut tile.shiftt),
+            ] {
+                ui.separator();
+                ui.strong(axis);
+                let mut mirror = *cm & TileDescriptor::MIRROR != 0;
+                let mut clamp = *cm & TileDescriptor::CLAMP != 0;
+                ui.checkbox(&mut mirror, "Mirror");
+                ui.checkbox(&mut clamp, "Clamp");
+                *cm =
+                    (mirror as u8 * TileDescriptor::MIRROR) | (clamp as u8 * TileDescriptor::CLAMP);
+                ui.label("Mask:");
+                ui.add(egui::DragValue::new(mask).range(0..=15));
+                ui.label("Shift:");
+                ui.add(egui::DragValue::new(shift).range(0..=15));
+            }
+        });
+    }
+
+    /// Renders the display list controls, the 3D viewport and the list of
+    /// executed commands.
+    fn render_model_view(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        ui.horizontal(|ui| {
+            ui.label("Display list:");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.dl_text)
+                    .desired_width(80.0)
+                    .font(egui::TextStyle::Monospace),
+            );
+            if ui.button("Here").clicked() {
+                if let Some(addr) = self.segmented_pos() {
+                    self.dl_text = format!("{:08X}", addr);
+                }
+            }
+
+            egui::ComboBox::from_id_salt("ucode_selection")
+                .selected_text(self.model_view.ucode.name())
+                .show_ui(ui, |ui| {
+                    for ucode in Microcode::ALL {
+                        ui.selectable_value(&mut self.model_view.ucode, ucode, ucode.name());
+                    }
+                });
+
+            if ui.button("Load").clicked() {
+                self.load_display_list(ctx);
+            }
+            if ui.button("Reset camera").clicked() {
+                self.model_view.reset_camera();
+            }
+
+            let exportable = self.model_view.mesh().is_some() && self.export_job.is_none();
+            ui.add_enabled_ui(exportable, |ui| {
+                ui.menu_button("Export", |ui| {
+                    for format in [ModelFormat::Glb, ModelFormat::Obj] {
+                        if ui.button(format.name()).clicked() {
+                            self.export_model(ctx, format);
+                            ui.close_menu();
+                        }
+                    }
+                });
+            });
+        });
+
+        if let Some(mesh) = self.model_view.mesh() {
+            ui.label(format!(
+                "{} commands, {} triangles, {} textures",
+                mesh.trace.len(),
+                mesh.triangles.len(),
+                mesh.textures.len()
+            ));
+            for warning in mesh.warnings.iter().take(4) {
+                ui.colored_label(ui.visuals().warn_fg_color, warning);
+            }
+
+            let mut clicked = None;
+            CollapsingHeader::new(format!("Textures ({})", mesh.textures.len()))
+                .id_salt("dl_textures")
+                .show(ui, |ui| {
+                    if ui.button("Find duplicates").clicked() {
+                        self.find_duplicates(ctx);
+                        self.view_state.show_duplicates = true;
+                    }
+                    clicked = self.model_view.texture_list(ui, &self.segments);
+                });
+            if let Some(index) = clicked {
+                self.view_dl_texture(index);
+            }
+        }
+
+        let size = egui::vec2(ui.available_width(), ui.available_height() * 0.65);
+        self.model_view.draw(ui, size);
+
+        ui.separator();
+        if let Some(addr) = self.model_view.command_list(ui, &self.segments) {
+            self.central_mode = CentralMode::Texture;
+            self.navigate_or_report(addr);
+        }
+    }
+
+    /// Configures the texture view to show a texture loaded by the display
+    /// list, including its palette, and switches to it.
+    ///
+    /// ### Arguments
+    /// * `index` - Index into the loaded mesh's textures.
+    fn view_dl_texture(&mut self, index: usize) {
+        let Some(desc) = self.model_view.mesh().and_then(|m| m.textures.get(index)) else {
+            return;
+        };
+        self.view_texture(desc.clone());
+    }
+
+    /// Configures the texture view to show a texture, including its
+    /// palette, and switches to it.
+    ///
+    /// ### Arguments
+    /// * `desc` - The texture to show.
+    fn view_texture(&mut self, desc: TextureDescriptor) {
+        let base = self.segment_base.as_ref().unwrap_or(&self.file);
+        let memory =
+            AddressSpace::new(&self.segments, &base.data).with_base_address(base.base_address);
+        let palette = desc.tlut_bytes(&memory);
+        self.set_palette(desc.tlut, palette);
+
+        self.update_image_format(desc.format);
+        self.sample32_tex.width = desc.width;
+        self.sample32_tex.height = desc.height;
+        self.sample32_tex.tile = desc.tile;
+        self.central_mode = CentralMode::Texture;
+        self.navigate_or_report(desc.addr);
+    }
+
+    /// Starts grouping the textures that have the same contents in the
+    /// background, among the display list's textures, the whole file or
+    /// both.
+    ///
+    /// ### Arguments
+    /// * `ctx` - The egui context, repainted as the analysis progresses.
+    fn find_duplicates(&mut self, ctx: &egui::Context) {
+        if let Some(id) = self.duplicates_job.take() {
+            self.jobs.cancel(id);
+        }
+        let mut regions: Vec<TextureDescriptor> = match self.model_view.mesh() {
+            Some(mesh) if self.duplicates.display_list => distinct_regions(&mesh.textures)
+                .into_iter()
+                .map(|i| mesh.textures[i].clone())
+                .collect(),
+            _ => vec![],
+        };
+        // Every region of the file, read as the texture view shows it
+        let template = self.duplicates.whole_file.then_some(TextureDescriptor {
+            addr: 0,
+            format: self.sample32_tex.format,
+            width: self.sample32_tex.width,
+            height: self.sample32_tex.height,
+            tlut: self.palette_addr,
+            tile: self.sample32_tex.tile,
+        });
+
+        let base = self.segment_base.as_ref().unwrap_or(&self.file);
+        let data = base.data.clone();
+        let base_address = base.base_address;
+        let segments = self.segments.clone();
+        let tolerance = self.duplicates.near.then_some(self.duplicates.tolerance);
+        self.duplicates_job = Some(self.jobs.spawn(
+            Some(ctx),
+            "Finding duplicate textures",
+            move |job| {
+                if let Some(template) = template {
+                    regions.extend(file_regions(
+                        data.len(),
+                        &template,
+                        |offset| match base_address {
+                            0 => segments.to_segmented(offset),
+                            base => Some(base.wrapping_add(offset as u32)),
+                        },
+                    ));
+                }
+
+                // Reading takes the first half of the progress bar
+                let memory = AddressSpace::new(&segments, &data).with_base_address(base_address);
+                let mut read = vec![];
+                for (i, desc) in regions.iter().enumerate() {
+                    if i % 256 == 0 {
+                        job.set_progress(i as f32 / regions.len() as f32 / 2.0);
+                        job.check_cancelled()?;
+                    }
+                    let texture = TextureData::read(desc, &memory, tolerance.is_some());
+                    if !texture.is_fill() {
+                        read.push((i, texture));
+                    }
+                }
+                let (indices, textures): (Vec<usize>, Vec<TextureData>) = read.into_iter().unzip();
+
+                let groups = group_duplicates_with_progress(&textures, tolerance, |fraction| {
+                    job.tick(0.5 + fraction / 2.0)
+                });
+                job.check_cancelled()?;
+                Ok(JobOutput::Duplicates {
+                    compared: textures.len(),
+                    groups: groups
+                        .into_iter()
+                        .map(|group| {
+                            group
+                                .into_iter()
+                                .map(|i| regions[indices[i]].clone())
+                                .collect()
+                        })
+                        .collect(),
+                })
+            },
+        ));
+    }
+
+    /// Lists the groups of duplicate textures with each member's address.
+    ///
+    /// ### Args
+    /// * `ctx` - egui context
+    fn show_duplicates_window(&mut self, ctx: &egui::Context) {
+        let mut analyze = false;
+        let mut clicked = None;
+        let running = self.duplicates_job.is_some();
+        let whole_file_label = format!(
+            "Whole file as {:?} {}x{}",
+            self.sample32_tex.format, self.sample32_tex.width, self.sample32_tex.height
+        );
+        let duplicates = &mut self.duplicates;
+        egui::Window::new("Duplicate Textures")
+            .open(&mut self.view_state.show_duplicates)
+            .show(ctx, |ui| {
+                ui.checkbox(&mut duplicates.display_list, "Display list textures");
+                ui.checkbox(&mut duplicates.whole_file, whole_file_label);
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut duplicates.near, "Near-identical");
+                    ui.add_enabled(
+                        duplicates.near,
+                        egui::Slider::new(&mut duplicates.tolerance, 0..=64).text("Tolerance"),
+                    );
+                });
+                ui.horizontal(|ui| {
+                    analyze = ui
+                        .add_enabled(!running, egui::Button::new("Analyze"))
+                        .clicked();
+                    if running {
+                        ui.spinner();
+                    }
+                });
+                ui.label(format!(
+                    "{} groups among {} textures",
+                    duplicates.groups.len(),
+                    duplicates.compared
+                ));
+                ui.separator();
+
+                egui::ScrollArea::vertical()
+                    .id_salt("duplicate_groups")
+                    .max_height(400.0)
+                    .show(ui, |ui| {
+                        for (n, group) in duplicates.groups.iter().enumerate() {
+                            let first = &group[0];
+                            ui.label(format!(
+                                "Group {}: {}x{}, {} copies",
+                                n + 1,
+                                first.width,
+                                first.height,
+                                group.len()
+                            ));
+                            for desc in group {
+                                ui.horizontal(|ui| {
+                                    ui.add_space(12.0);
+                                    if address_link(ui, &self.segments, desc.addr) {
+                                        clicked = Some(desc.clone());
+                                    }
+                                    ui.monospace(format!("{:?}", desc.format));
+                                });
+                            }
+                        }
+                    });
+            });
+
+        if analyze {
+            self.find_duplicates(ctx);
+        }
+        if let Some(desc) = clicked {
+            self.view_texture(desc);
+        }
+    }
+
+    fn set_palette(&mut self, addr: Option<u32>, palette: Option<Vec<u8>>) {
+        self.palette_addr = addr;
+        self.sample32_tex.palette = palette.clone();
+        self.preview_tex.palette = palette;
+    }
+
+    /// Asks for a path and writes the loaded mesh with its textures in the
+    /// background.
+    ///
+    /// ### Arguments
+    /// * `ctx` - The egui context, repainted as the export progresses.
+    /// * `format` - The file format to write.
+    fn export_model(&mut self, ctx: &egui::Context, format: ModelFormat) {
+        let Some(mesh) = self.model_view.mesh() else {
+            return;
+        };
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter(format.name(), &[format.extension()])
+            .set_file_name(format!(
+                "dl_{:08X}.{}",
+                self.model_view.dl_addr,
+                format.extension()
+            ))
+            .save_file()
+        else {
+            return;
+        };
+
+        let mesh = mesh.clone();
+        let base = self.segment_base.as_ref().unwrap_or(&self.file);
+        let data = base.data.clone();
+        let base_address = base.base_address;
+        let segments = self.segments.clone();
+        let name = format!("Exporting {}", path.display());
+        self.export_job = Some(self.jobs.spawn(Some(ctx), &name, move |job| {
+            // Decoding takes the progress bar; writing is quick after that
+            let memory = AddressSpace::new(&segments, &data).with_base_address(base_address);
+            let mut textures = vec![];
+            for (i, desc) in mesh.textures.iter().enumerate() {
+                job.set_progress(i as f32 / mesh.textures.len() as f32);
+                job.check_cancelled()?;
+                textures.push(decode_texture(&memory, desc));
+            }
+            format.write(&path, &mesh, &textures)?;
+            Ok(JobOutput::Exported)
+        }));
+    }
+
+    fn load_display_list(&mut self, ctx: &egui::Context) {
+        let Some(addr) = parse_address(&self.dl_text) else {
+            self.error_message = Some(format!("Invalid address: {}", self.dl_text));
+            return;
+        };
+
+        self.model_view.dl_addr = addr;
+        let base = self.segment_base.as_ref().unwrap_or(&self.file);
+        let memory =
+            AddressSpace::new(&self.segments, &base.data).with_base_address(base.base_address);
+        if let Err(e) = self.model_view.load(ctx, &memory) {
+            self.error_message = Some(e.to_string());
         }
     }
 
@@ -203,6 +1445,23 @@ impl Motex {
 
         ui.add_space(8.0);
 
+        CollapsingHeader::new("Combiner")
+            .default_open(false)
+            .show(ui, |ui| {
+                self.sample32_tex.combiner.ui(ui);
+            });
+        self.preview_tex.combiner = self.sample32_tex.combiner;
+
+        ui.add_space(8.0);
+
+        CollapsingHeader::new("Onion Skin")
+            .default_open(false)
+            .show(ui, |ui| {
+                self.render_onion_controls(ui);
+            });
+
+        ui.add_space(8.0);
+
         CollapsingHeader::new("Color Information")
             .default_open(true)
             .show(ui, |ui| {
@@ -217,7 +1476,7 @@ impl Motex {
     /// * `ctx` - The egui context.
     fn render_right_panel(&mut self, ctx: &egui::Context) {
         SidePanel::right("right_panel")
-            .max_width(150.0)
+            .max_width(170.0)
             .resizable(false)
             .show(ctx, |ui| {
                 ScrollArea::vertical().show(ui, |ui| {
@@ -239,19 +1498,128 @@ impl Motex {
             ui.label("Position:");
             ui.monospace(format!("0x{:08X}", self.file_pos));
         });
+        ui.horizontal(|ui| {
+            ui.label("Base:");
+            ui.add(egui::DragValue::new(&mut self.file.base_address).hexadecimal(8, false, true));
+        });
+        if self.file.base_address != 0 {
+            ui.horizontal(|ui| {
+                ui.label("Address:");
+                ui.monospace(format!("0x{:08X}", self.file.address_of(self.file_pos)));
+            });
+        }
+        if let Some(addr) = self.segmented_pos() {
+            ui.horizontal(|ui| {
+                ui.label("Segmented:");
+                if address_link(ui, &self.segments, addr) {
+                    self.navigate_or_report(addr);
+                }
+            });
+        }
+        ui.horizontal(|ui| {
+            ui.label("Go to:");
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.goto_text)
+                    .desired_width(80.0)
+                    .font(egui::TextStyle::Monospace),
+            );
+            if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                match parse_address(&self.goto_text) {
+                    Some(addr) => self.navigate_or_report(addr),
+                    None => {
+                        self.error_message = Some(format!("Invalid address: {}", self.goto_text))
+                    }
+                }
+            }
+        });
+        if let Some(error) = &self.error_message {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
 
         ui.add_space(8.0);
 
-        // Preview with scroll bar
-        ScrollArea::vertical().show(ui, |ui| {
-            // Ensure the content is taller than the available height to trigger the scroll bar
-            ui.set_min_height(2000.0);
+        ui.horizontal_top(|ui| {
+            let size = egui::vec2(12.0, ui.available_height().max(100.0));
+            if let Some(offset) = self.minimap.draw(
+                ui,
+                ctx,
+                &self.file.data,
+                self.file.generation,
+                self.file_pos,
+                size,
+            ) {
+                self.file_pos = offset;
+            }
+
+            // Preview with scroll bar
+            ScrollArea::vertical().show(ui, |ui| {
+                // Ensure the content is taller than the available height to trigger the scroll bar
+                ui.set_min_height(2000.0);
+
+                self.preview_tex.width = 128;
+                self.preview_tex.height = ui.available_height() as usize - 5;
+                let response = self.preview_tex.draw(
+                    &self.file.data,
+                    self.file.generation,
+                    self.file_pos,
+                    ui,
+                    ctx,
+                );
+                self.handle_preview_input(response);
+            });
+        });
+    }
+
+    /// Reports the hovered pixel of the preview strip and jumps to the row
+    /// that was clicked.
+    /// ### Arguments
+    /// * `response` - The response of the preview image.
+    fn handle_preview_input(&mut self, response: Option<egui::Response>) {
+        let Some(response) = response else {
+            return;
+        };
+        let Some(raw) = self
+            .preview_tex
+            .hovered_raw_pixel(&self.file.data, self.file_pos)
+        else {
+            return;
+        };
 
-            self.preview_tex.width = 128;
-            self.preview_tex.height = ui.available_height() as usize - 5;
-            self.preview_tex
-                .draw(&self.file.data, self.file_pos, ui, ctx);
+        let row_offset =
+            self.file_pos + image_byte_size(self.preview_tex.format, self.preview_tex.width, raw.y);
+        let response = response.on_hover_ui_at_pointer(|ui| {
+            ui.label(format!("Pixel: {}, {}", raw.x, raw.y));
+            ui.monospace(format!("Offset: 0x{:08X}", raw.offset));
         });
+        if response.clicked() {
+            self.file_pos = row_offset.min(self.file.data.len());
+        }
+    }
+
+    /// Renders the hex panel, which dumps the bytes at the current file
+    /// position and highlights whatever the central panel is inspecting.
+    /// ### Arguments
+    /// * `ctx` - The egui context.
+    fn render_hex_panel(&mut self, ctx: &egui::Context) {
+        if self.file.data.is_empty() {
+            return;
+        }
+
+        TopBottomPanel::bottom("hex_panel")
+            .resizable(true)
+            .default_height(120.0)
+            .show(ctx, |ui| {
+                let rows = (ui.available_height() / 16.0).max(1.0) as usize;
+                let highlight = self.hex_highlight.as_ref();
+                hex_dump(
+                    ui,
+                    &self.file.data,
+                    visible_start(self.file_pos, rows, highlight),
+                    rows,
+                    highlight,
+                    self.file.base_address,
+                );
+            });
     }
 
     /// Opens the About window and renders the contents of the window
@@ -288,7 +1656,28 @@ impl Motex {
             egui::menu::bar(ui, |ui| {
                 ui.menu_button("File", |ui| {
                     if ui.add(egui::Button::new("Open")).clicked() {
-                        self.open_file_dialog();
+                        self.open_file_dialog(ctx);
+                        ui.close_menu();
+                    }
+                    if ui
+                        .add_enabled(
+                            !self.file.data.is_empty(),
+                            egui::Button::new("Compare With..."),
+                        )
+                        .clicked()
+                    {
+                        self.compare_file_dialog();
+                        ui.close_menu();
+                    }
+                    if ui
+                        .add_enabled(!self.file.data.is_empty(), egui::Button::new("Find PNG..."))
+                        .clicked()
+                    {
+                        self.find_png_dialog(ctx);
+                        ui.close_menu();
+                    }
+                    if ui.add(egui::Button::new("Import Model")).clicked() {
+                        self.import_model_dialog();
                         ui.close_menu();
                     }
                     if ui.add(egui::Button::new("Quit")).clicked() {
@@ -296,6 +1685,18 @@ impl Motex {
                     }
                 });
 
+                if ui.add(egui::Button::new("Search")).clicked() {
+                    self.view_state.show_search = true;
+                }
+
+                if ui.add(egui::Button::new("Duplicates")).clicked() {
+                    self.view_state.show_duplicates = true;
+                }
+
+                if ui.add(egui::Button::new("Segments")).clicked() {
+                    self.view_state.show_segments = true;
+                }
+
                 if ui.add(egui::Button::new("Options")).clicked() {
                     self.view_state.show_options = true;
                 }
@@ -307,9 +1708,37 @@ impl Motex {
         });
     }
 
-    fn open_file_dialog(&mut self) {
+    /// Opens the search window on Ctrl+F and moves between hits with F3
+    /// and Shift+F3.
+    /// ### Arguments
+    /// * `ctx` - The egui context.
+    fn handle_search_keys(&mut self, ctx: &egui::Context) {
+        let (find, next, previous) = ctx.input(|i| {
+            let f3 = i.key_pressed(egui::Key::F3);
+            (
+                i.modifiers.command && i.key_pressed(egui::Key::F),
+                f3 && !i.modifiers.shift,
+                f3 && i.modifiers.shift,
+            )
+        });
+        if find {
+            self.view_state.show_search = true;
+        }
+        let hit = if next {
+            self.search.next_hit(self.file_pos)
+        } else if previous {
+            self.search.previous_hit(self.file_pos)
+        } else {
+            None
+        };
+        if let Some(offset) = hit {
+            self.file_pos = offset;
+        }
+    }
+
+    fn open_file_dialog(&mut self, ctx: &egui::Context) {
         if let Some(path) = rfd::FileDialog::new().pick_file() {
-            match self.open_file(&path) {
+            match self.open_file(ctx, &path) {
                 Ok(()) => {}
                 Err(e) => {
                     eprintln!("Failed to open file: {}", e);
@@ -319,30 +1748,461 @@ impl Motex {
         }
     }
 
+    fn compare_file_dialog(&mut self) {
+        let Some(path) = rfd::FileDialog::new().pick_file() else {
+            return;
+        };
+
+        match Comparison::open(&path) {
+            Ok(comparison) => {
+                self.comparison = Some(comparison);
+                self.diff_outdated = true;
+                self.sync_highlights();
+                self.view_state.show_compare = true;
+            }
+            Err(e) => {
+                eprintln!("Failed to open file to compare: {}", e);
+                self.error_message = Some(format!("Failed to open file to compare: {}", e));
+            }
+        }
+    }
+
+    /// Shows the ranges that differ from the compared file, with controls
+    /// to align the two files.
+    ///
+    /// ### Args
+    /// * `ctx` - egui context
+    fn show_compare_window(&mut self, ctx: &egui::Context) {
+        let Some(comparison) = &mut self.comparison else {
+            self.view_state.show_compare = false;
+            return;
+        };
+
+        let mut shifted = false;
+        let mut align = false;
+        let mut changed = false;
+        let mut close = false;
+        let mut jump = None;
+        egui::Window::new("Compare")
+            .open(&mut self.view_state.show_compare)
+            .show(ctx, |ui| {
+                ui.label(format!("Against: {}", comparison.other.path.display()));
+                ui.horizontal(|ui| {
+                    ui.label("Shift:");
+                    shifted |= ui
+                        .add(egui::DragValue::new(&mut comparison.shift))
+                        .changed();
+                    if self.align_job.is_some() {
+                        ui.label("Aligning...");
+                    } else if ui.button("Auto align").clicked() {
+                        align = true;
+                    }
+                });
+                ui.horizontal(|ui| {
+                    changed |= ui
+                        .checkbox(&mut self.tint_differences, "Tint differences")
+                        .changed();
+                    if ui.button("Stop comparing").clicked() {
+                        close = true;
+                    }
+                });
+
+                if self.diff_job.is_some() {
+                    ui.label("Comparing...");
+                } else {
+                    ui.label(format!(
+                        "{} ranges, {} bytes differ",
+                        comparison.ranges.len(),
+                        comparison.differing_bytes()
+                    ));
+                }
+                ui.separator();
+
+                let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+                egui::ScrollArea::vertical()
+                    .id_salt("diff_ranges")
+                    .max_height(300.0)
+                    .show_rows(ui, row_height, comparison.ranges.len(), |ui, rows| {
+                        for range in &comparison.ranges[rows] {
+                            let text = format!(
+                                "0x{:08X}-0x{:08X} ({} bytes)",
+                                range.start,
+                                range.end,
+                                range.len()
+                            );
+                            let current = range.contains(&self.file_pos);
+                            if ui
+                                .selectable_label(current, egui::RichText::new(text).monospace())
+                                .clicked()
+                            {
+                                jump = Some(range.start);
+                            }
+                        }
+                    });
+            });
+
+        if shifted {
+            // A shift typed in wins over one still being searched for
+            if let Some(id) = self.align_job.take() {
+                self.jobs.cancel(id);
+            }
+            self.diff_outdated = true;
+        }
+        if align {
+            self.start_alignment(ctx);
+        }
+        if let Some(offset) = jump {
+            self.file_pos = offset;
+        }
+        if close {
+            self.comparison = None;
+            for id in [self.diff_job.take(), self.align_job.take()]
+                .into_iter()
+                .flatten()
+            {
+                self.jobs.cancel(id);
+            }
+            self.view_state.show_compare = false;
+        }
+        if changed || close {
+            self.sync_highlights();
+        }
+    }
+
+    fn find_png_dialog(&mut self, ctx: &egui::Context) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("PNG", &["png"])
+            .pick_file()
+        else {
+            return;
+        };
+
+        match load_png(&path) {
+            Ok((width, height, rgba)) => {
+                self.png_search = Some(PngSearch {
+                    path,
+                    width,
+                    height,
+                    rgba,
+                    tolerance: 0,
+                    hits: vec![],
+                });
+                self.search_png(ctx);
+                self.view_state.show_find_png = true;
+            }
+            Err(e) => {
+                eprintln!("Failed to load PNG: {}", e);
+                self.error_message = Some(format!("Failed to load PNG: {}", e));
+            }
+        }
+    }
+
+    /// Starts a background search of the open file for the PNG in
+    /// `png_search`.
+    ///
+    /// ### Arguments
+    /// * `ctx` - The egui context, repainted as the search progresses.
+    fn search_png(&mut self, ctx: &egui::Context) {
+        let Some(search) = &mut self.png_search else {
+            return;
+        };
+        if let Some(id) = self.png_job.take() {
+            self.jobs.cancel(id);
+        }
+        search.hits.clear();
+        let data = self.file.data.clone();
+        let (width, height, tolerance) = (search.width, search.height, search.tolerance);
+        let rgba = search.rgba.clone();
+        self.png_job = Some(self.jobs.spawn(Some(ctx), "Searching for PNG", move |job| {
+            let hits = find_texture_with_progress(&data, width, height, &rgba, tolerance, |f| {
+                job.tick(f)
+            })?;
+            job.check_cancelled()?;
+            Ok(JobOutput::PngHits(hits))
+        }));
+    }
+
+    /// Lists where the searched PNG was found. Clicking a hit shows the
+    /// texture there.
+    ///
+    /// ### Args
+    /// * `ctx` - egui context
+    fn show_find_png_window(&mut self, ctx: &egui::Context) {
+        let Some(search) = &mut self.png_search else {
+            self.view_state.show_find_png = false;
+            return;
+        };
+
+        let searching = self.png_job.is_some();
+        let mut search_again = false;
+        let mut selected = None;
+        egui::Window::new("Find PNG")
+            .open(&mut self.view_state.show_find_png)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "{} ({}x{})",
+                    search.path.display(),
+                    search.width,
+                    search.height
+                ));
+                ui.horizontal(|ui| {
+                    ui.add(egui::Slider::new(&mut search.tolerance, 0..=4).text("Tolerance"))
+                        .on_hover_text(
+                            "Allowed difference per channel, in steps of each format, for \
+                             textures from lossy encoders",
+                        );
+                    search_again = ui
+                        .add_enabled(!searching, egui::Button::new("Search"))
+                        .clicked();
+                });
+                if searching {
+                    ui.label("Searching...");
+                } else {
+                    ui.label(format!("{} hits", search.hits.len()));
+                }
+                ui.separator();
+
+                let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+                egui::ScrollArea::vertical()
+                    .id_salt("png_hits")
+                    .max_height(300.0)
+                    .show_rows(ui, row_height, search.hits.len(), |ui, rows| {
+                        for hit in &search.hits[rows] {
+                            let text = format!("0x{:08X} {:?}", hit.offset, hit.format);
+                            let current = hit.offset == self.file_pos && hit.format == self.format;
+                            if ui
+                                .selectable_label(current, egui::RichText::new(text).monospace())
+                                .clicked()
+                            {
+                                selected = Some((*hit, search.width, search.height));
+                            }
+                        }
+                    });
+            });
+
+        if search_again {
+            self.search_png(ctx);
+        }
+        if let Some((hit, width, height)) = selected {
+            self.update_image_format(hit.format);
+            self.sample32_tex.width = width;
+            self.sample32_tex.height = height;
+            self.file_pos = hit.offset;
+            self.central_mode = CentralMode::Texture;
+        }
+    }
+
+    fn import_model_dialog(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Models", &["obj", "gltf", "glb"])
+            .pick_file()
+        else {
+            return;
+        };
+
+        match load_model(&path) {
+            Ok(model) => {
+                self.build_options.name = path
+                    .file_stem()
+                    .map(|s| {
+                        s.to_string_lossy()
+                            .replace(|c: char| !c.is_alphanumeric(), "_")
+                    })
+                    .unwrap_or_else(|| "model".to_owned());
+                self.build_options.scale = model.auto_scale();
+                self.imported_model = Some(model);
+                self.view_state.show_import = true;
+            }
+            Err(e) => {
+                eprintln!("Failed to import model: {}", e);
+                self.error_message = Some(format!("Failed to import model: {}", e));
+            }
+        }
+    }
+
+    /// Shows the settings for the imported model and generates the display
+    /// list when asked.
+    ///
+    /// ### Args
+    /// * `ctx` - egui context
+    fn show_import_window(&mut self, ctx: &egui::Context) {
+        let Some(model) = &self.imported_model else {
+            self.view_state.show_import = false;
+            return;
+        };
+
+        let mut generate = false;
+        egui::Window::new("Import Model")
+            .open(&mut self.view_state.show_import)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "{} triangles, {} textures",
+                    model.mesh.triangles.len(),
+                    model.textures.len()
+                ));
+
+                let options = &mut self.build_options;
+                egui::Grid::new("import_options").show(ui, |ui| {
+                    ui.label("Name:");
+                    ui.text_edit_singleline(&mut options.name);
+                    ui.end_row();
+
+                    ui.label("Scale:");
+                    ui.horizontal(|ui| {
+                        ui.add(egui::DragValue::new(&mut options.scale).speed(0.1));
+                        if ui.button("Auto").clicked() {
+                            options.scale = model.auto_scale();
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label("Segment:");
+                    ui.add(egui::DragValue::new(&mut options.segment).range(1..=15));
+                    ui.end_row();
+
+                    ui.label("Lighting:");
+                    ui.checkbox(&mut options.lighting, "Use normals");
+                    ui.end_row();
+                });
+
+                generate = ui.button("Generate").clicked();
+            });
+
+        if generate {
+            self.generate_display_list(ctx);
+        }
+    }
+
+    /// Writes the imported model as a binary blob and C source, then opens
+    /// the blob and previews the display list.
+    fn generate_display_list(&mut self, ctx: &egui::Context) {
+        let Some(model) = &self.imported_model else {
+            return;
+        };
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Binary", &["bin"])
+            .set_file_name(format!("{}.bin", self.build_options.name))
+            .save_file()
+        else {
+            return;
+        };
+
+        let result = build_display_list(model, &self.build_options).and_then(|generated| {
+            std::fs::write(&path, &generated.binary)?;
+            std::fs::write(path.with_extension("c"), &generated.c_source)?;
+            Ok(generated)
+        });
+        let generated = match result {
+            Ok(generated) => generated,
+            Err(e) => {
+                eprintln!("Failed to generate display list: {}", e);
+                self.error_message = Some(format!("Failed to generate display list: {}", e));
+                return;
+            }
+        };
+
+        // The model is loaded right away, so the small generated file is
+        // read here rather than in a job
+        match BinFile::open(&path) {
+            Ok(file) => self.set_file(file),
+            Err(e) => {
+                self.error_message = Some(format!("Failed to open file: {}", e));
+                return;
+            }
+        }
+        let segment = self.build_options.segment;
+        self.segments.set(segment, 0, SegmentSource::OpenFile);
+        self.dl_text = format!("{:08X}", generated.dl_address(segment));
+        self.model_view.ucode = Microcode::F3dex2;
+        self.central_mode = CentralMode::Model;
+        self.load_display_list(ctx);
+    }
+
     /// This function is responsible for rendering the bottom bar of the application.
     /// The bar displays the path and size of the file that is open.
     ///
     /// ### Args
     /// * `ctx` - egui context
-    fn render_bottom_bar(&self, ctx: &egui::Context) {
+    fn render_bottom_bar(&mut self, ctx: &egui::Context) {
         TopBottomPanel::bottom("bottom_bar").show(ctx, |ui| {
             // If a file is open, display the path.
             if self.file.path.exists() {
                 ui.horizontal(|ui| {
                     ui.label(format!("File: {}", self.file.path.display()));
+                    if let Some(base) = &self.segment_base {
+                        let back = ui
+                            .button("Back to open file")
+                            .on_hover_text(format!(
+                                "Showing a segment source; {} is still the open file",
+                                base.path.display()
+                            ))
+                            .clicked();
+                        if back {
+                            self.show_segment_base();
+                            self.file_pos = self.file_pos.min(self.file.data.len());
+                        }
+                    }
                     ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                         ui.label(format!("Size: 0x{:X}", self.file.data.len()));
+                        if self.file.is_mapped() {
+                            ui.label(
+                                egui::RichText::new("Mapped").color(ui.visuals().warn_fg_color),
+                            )
+                            .on_hover_text(
+                                "The file is memory-mapped and read from disk as needed. \
+                                 It is not reloaded when it changes, and motex crashes if \
+                                 another program shortens it while it is open.",
+                            );
+                        }
+                        ui.separator();
+                        self.render_watch_status(ui);
                     });
                 });
             }
         });
     }
+
+    /// Shows whether the open file is reloaded on change, with a toggle to
+    /// pause it and a note after each reload.
+    /// ### Arguments
+    /// * `ui` - The egui ui to draw into.
+    fn render_watch_status(&mut self, ui: &mut egui::Ui) {
+        if self.segment_base.as_ref().unwrap_or(&self.file).is_mapped() {
+            ui.colored_label(ui.visuals().warn_fg_color, "Auto reload off")
+                .on_hover_text("Memory-mapped files are not reloaded when they change");
+            return;
+        }
+        let (text, color) = if self.watcher.paused {
+            ("Auto reload paused", ui.visuals().warn_fg_color)
+        } else {
+            ("Auto reload", egui::Color32::from_rgb(80, 180, 80))
+        };
+        if ui
+            .add(egui::Button::new(egui::RichText::new(text).color(color)).frame(false))
+            .on_hover_text("Click to pause or resume reloading when the file changes")
+            .clicked()
+        {
+            self.watcher.paused = !self.watcher.paused;
+        }
+
+        if let Some(reloaded) = self.watcher.last_reload {
+            let age = reloaded.elapsed().as_secs_f32();
+            if age < 3.0 {
+                ui.colored_label(ui.visuals().warn_fg_color, "Reloaded");
+                ui.ctx().request_repaint_after_secs(3.0 - age);
+            }
+        }
+    }
 }
 
 impl eframe::App for Motex {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         ctx.input(|i| {
-            if self.file.data.is_empty() {
+            // The model viewport uses the scroll wheel for zooming, and
+            // Ctrl+wheel zooms the texture view
+            let over_model = self.central_mode == CentralMode::Model
+                && self.model_view.is_hovered(i.pointer.hover_pos());
+            if self.file.data.is_empty() || over_model || i.modifiers.command {
                 return;
             }
 
@@ -356,21 +2216,32 @@ impl eframe::App for Motex {
             // Scroll 4 lines at a time
             let scroll_factor = 4;
 
-            self.file_pos = (self.file_pos as i32
+            // 64-bit, so positions past 2GB in mapped files do not overflow
+            self.file_pos = (self.file_pos as i64
                 + (scroll_dir
                     * scroll_factor
                     * (self.preview_tex.width as f32 * bpp_from_image_type(self.preview_tex.format)) // TODO maybe we don't want to change the scroll speed based on the currently-selected format
-                        as i32))
+                        as i64))
                 .max(0)
-                .min(self.file.data.len() as i32) as usize;
+                .min(self.file.data.len() as i64) as usize;
         });
 
         // Open dropped files
         if ctx.input(|i| !i.raw.dropped_files.is_empty()) {
             for file in ctx.input(|i| i.raw.dropped_files.clone()) {
-                let _ = self.open_file(&file.path.unwrap());
+                let _ = self.open_file(ctx, &file.path.unwrap());
             }
         }
+        let watched = self.segment_base.as_ref().unwrap_or(&self.file);
+        if !watched.data.is_empty() && self.watcher.poll(&watched.path) {
+            self.reload_file(ctx);
+        }
+        if self.watcher.is_active() {
+            ctx.request_repaint_after(POLL_INTERVAL);
+        }
+        self.poll_jobs();
+        self.update_minimap(ctx);
+        self.handle_search_keys(ctx);
         self.pre_update(ctx);
 
         self.create_top_bar(ctx);
@@ -379,10 +2250,22 @@ impl eframe::App for Motex {
 
         self.render_right_panel(ctx);
 
-        self.render_central_panel(ctx);
-
         self.render_bottom_bar(ctx);
 
+        self.render_jobs_panel(ctx);
+
+        // Work out the highlight before the hex panel is drawn, so it does
+        // not trail the pointer by a frame
+        self.hex_highlight = match self.central_mode {
+            CentralMode::Vertices => self
+                .vtx_view
+                .hovered_range(ctx.pointer_hover_pos(), self.file_pos),
+            _ => None,
+        };
+        self.render_hex_panel(ctx);
+
+        self.render_central_panel(ctx);
+
         let show_about = &mut self.view_state.show_about;
         if *show_about {
             self.show_about_window(ctx);
@@ -392,20 +2275,65 @@ impl eframe::App for Motex {
         if *show_options {
             options_window(ctx, show_options, &mut self.appearance);
         }
+
+        let show_segments = &mut self.view_state.show_segments;
+        if *show_segments {
+            segment_window(ctx, show_segments, &mut self.segments);
+        }
+
+        if self.view_state.show_import {
+            self.show_import_window(ctx);
+        }
+
+        if self.view_state.show_compare {
+            self.show_compare_window(ctx);
+        }
+
+        if self.view_state.show_find_png {
+            self.show_find_png_window(ctx);
+        }
+
+        if self.view_state.show_duplicates {
+            self.show_duplicates_window(ctx);
+        }
+
+        if self.view_state.show_search {
+            match search_window(
+                ctx,
+                &mut self.view_state.show_search,
+                &mut self.search,
+                self.search_job.is_some(),
+                self.file_pos,
+            ) {
+                Some(SearchRequest::Run) => self.start_search(ctx),
+                Some(SearchRequest::Jump(offset)) => self.file_pos = offset,
+                None => {}
+            }
+        }
+
+        // After everything that can change the open file or the shift
+        if self.diff_outdated {
+            self.start_diff(ctx);
+        }
     }
 }
 
-fn bpp_from_image_type(image_type: ImageType) -> f32 {
-    match image_type {
-        ImageType::I1 => 0.125,
-        ImageType::I4 => 0.5,
-        ImageType::I8 => 1.0,
-        ImageType::Ia4 => 0.5,
-        ImageType::Ia8 => 1.0,
-        ImageType::Ia16 => 2.0,
-        ImageType::Ci4 => 0.5,
-        ImageType::Ci8 => 1.0,
-        ImageType::Rgba16 => 2.0,
-        ImageType::Rgba32 => 4.0,
-    }
+/// Asks whether to memory-map a large file rather than read it into memory.
+///
+/// ### Arguments
+///
+/// * `path` - The file about to be opened.
+fn confirm_map(path: &Path) -> bool {
+    let answer = rfd::MessageDialog::new()
+        .set_level(rfd::MessageLevel::Warning)
+        .set_title("Large file")
+        .set_description(format!(
+            "{} is large. Map it instead of reading it into memory?\n\n\
+             A mapped file is read from disk as needed and is not reloaded when it \
+             changes. If another program shortens it while it is open, motex crashes.",
+            path.display()
+        ))
+        .set_buttons(rfd::MessageButtons::YesNo)
+        .show();
+    answer == rfd::MessageDialogResult::Yes
 }
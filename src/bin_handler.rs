@@ -1,6 +1,72 @@
-use std::path::{Path, PathBuf};
+use std::{
+    fs::File,
+    ops::Deref,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
 
-use anyhow::Result;
+use anyhow::{bail, Result};
+use memmap2::{Mmap, MmapOptions};
+
+use crate::savestate::{extract_rdram, is_savestate_path, RDRAM_BASE};
+
+/// Files at least this large are memory-mapped instead of read.
+pub const MMAP_THRESHOLD: u64 = 256 * 1024 * 1024;
+
+/// The bytes of a file, either read into memory or mapped from disk.
+///
+/// Both dereference to `[u8]`, so code reading the file does not need to
+/// know which it is. Cloning shares the bytes, so background jobs can keep
+/// reading a file while it is replaced in the UI.
+#[derive(Clone)]
+pub enum FileData {
+    Owned(Arc<Vec<u8>>),
+    /// Pages are read from disk on demand, so huge files open instantly and
+    /// only the parts being viewed take up memory.
+    Mapped(Arc<Mmap>),
+}
+
+impl Default for FileData {
+    fn default() -> Self {
+        FileData::Owned(Arc::default())
+    }
+}
+
+impl Deref for FileData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            FileData::Owned(data) => data,
+            FileData::Mapped(map) => map,
+        }
+    }
+}
+
+impl AsRef<[u8]> for FileData {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl<T: AsRef<[u8]> + ?Sized> PartialEq<T> for FileData {
+    fn eq(&self, other: &T) -> bool {
+        **self == *other.as_ref()
+    }
+}
+
+impl std::fmt::Debug for FileData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self {
+            FileData::Owned(_) => "Owned",
+            FileData::Mapped(_) => "Mapped",
+        };
+        write!(f, "FileData::{}({} bytes)", kind, self.len())
+    }
+}
 
 /// A simple struct to hold the path and data of a binary file.
 #[derive(Debug, Default, PartialEq)]
@@ -8,12 +74,27 @@ pub struct BinFile {
     /// The file path as a `PathBuf`.
     pub path: PathBuf,
     /// The raw bytes of the file.
-    pub data: Vec<u8>,
+    pub data: FileData,
+    /// The virtual address of the first byte, e.g. `0x80000000` for an
+    /// RDRAM dump. Zero if the file is not mapped anywhere.
+    pub base_address: u32,
+    /// Identifies this load of the file; every load gets a new value, so
+    /// caches keyed on it are dropped when a file is reopened or reloaded.
+    pub generation: u64,
+}
+
+/// Hands out `BinFile::generation` values. Zero is left for empty files.
+fn next_generation() -> u64 {
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+    NEXT.fetch_add(1, Ordering::Relaxed)
 }
 
 impl BinFile {
     /// Creates a new `BinFile` instance from the specified path.
     ///
+    /// Files of `MMAP_THRESHOLD` bytes or more are memory-mapped; callers
+    /// that have not asked the user first should use `read` instead.
+    ///
     /// # Arguments
     ///
     /// * `path` - A path to the binary file to be read.
@@ -23,9 +104,118 @@ impl BinFile {
     /// Returns a `Result` containing the `BinFile` instance if successful,
     /// or an `std::io::Error` if the file cannot be read.
     pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if std::fs::metadata(&path)?.len() >= MMAP_THRESHOLD {
+            return Self::map(path);
+        }
+        Self::read(path)
+    }
+
+    /// Reads a whole file into memory, however large it is.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A path to the binary file to be read.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self> {
         let path = path.as_ref().to_path_buf();
         let data = std::fs::read(&path)?;
 
-        Ok(Self { path, data })
+        Ok(Self {
+            path,
+            data: FileData::Owned(Arc::new(data)),
+            base_address: 0,
+            generation: next_generation(),
+        })
+    }
+
+    /// Memory-maps a file rather than reading it.
+    ///
+    /// The mapping is private and read-only, so nothing this process does
+    /// can write through to the file. It does not protect against other
+    /// processes: bytes they change in place show up in the map, and if the
+    /// file is truncated while it is mapped, reading a page past its new
+    /// end raises `SIGBUS` and ends the process. Nothing here can prevent
+    /// that, so mapped files are not reloaded when they change, and the UI
+    /// asks before mapping a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A path to the binary file to be mapped.
+    pub fn map<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = File::open(&path)?;
+        let len = file.metadata()?.len();
+        // SAFETY: not fully sound. The map stays valid only while no other
+        // process truncates the file; see above for what happens if one
+        // does.
+        let map = unsafe { MmapOptions::new().map_copy_read_only(&file)? };
+        if map.len() as u64 != len {
+            bail!("{} changed while it was being mapped", path.display());
+        }
+
+        Ok(Self {
+            path,
+            data: FileData::Mapped(Arc::new(map)),
+            base_address: 0,
+            generation: next_generation(),
+        })
+    }
+
+    /// Opens a file the way the user expects: savestates as their RDRAM,
+    /// anything else as it is.
+    ///
+    /// Files named like a savestate that do not hold one, such as other zip
+    /// archives, are opened as raw bytes. Files are always read into
+    /// memory; use `map` for files the user agreed to map.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A path to the file to open.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if is_savestate_path(path) {
+            if let Ok(file) = Self::from_savestate(path) {
+                return Ok(file);
+            }
+        }
+        Self::read(path)
+    }
+
+    /// Opens a Project64 or mupen64plus savestate and presents its RDRAM
+    /// as the file's data, mapped at `0x80000000`.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A path to the savestate.
+    pub fn from_savestate<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let (_, data) = extract_rdram(&std::fs::read(&path)?)?;
+
+        Ok(Self {
+            path,
+            data: FileData::Owned(Arc::new(data)),
+            base_address: RDRAM_BASE,
+            generation: next_generation(),
+        })
+    }
+
+    /// Whether the data is memory-mapped rather than read into memory.
+    pub fn is_mapped(&self) -> bool {
+        matches!(self.data, FileData::Mapped(_))
+    }
+
+    /// Returns the virtual address of a file offset.
+    pub fn address_of(&self, offset: usize) -> u32 {
+        self.base_address.wrapping_add(offset as u32)
+    }
+
+    /// Returns the file offset of a virtual address, if the file has a base
+    /// address and the address falls inside the file.
+    pub fn offset_of(&self, addr: u32) -> Option<usize> {
+        if self.base_address == 0 {
+            return None;
+        }
+        let offset = addr.checked_sub(self.base_address)? as usize;
+        (offset < self.data.len()).then_some(offset)
     }
 }
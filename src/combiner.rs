@@ -0,0 +1,136 @@
+use eframe::egui;
+
+/// A few common color combiner setups, used to preview textures the way
+/// games tint them with the primitive and environment colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CombineMode {
+    /// The texture as decoded.
+    #[default]
+    Texel0,
+    /// `TEXEL0 * PRIMITIVE`, as in `G_CC_MODULATEIA_PRIM`.
+    ModulatePrim,
+    /// `TEXEL0 * ENVIRONMENT`.
+    ModulateEnv,
+    /// `(PRIMITIVE - ENVIRONMENT) * TEXEL0 + ENVIRONMENT`, which maps black
+    /// texels to the environment color and white texels to the primitive
+    /// color.
+    LerpEnvPrim,
+    /// `TEXEL0 * PRIMITIVE * ENVIRONMENT`, as used by two-cycle setups that
+    /// tint twice.
+    ModulatePrimEnv,
+}
+
+impl CombineMode {
+    pub const ALL: [CombineMode; 5] = [
+        CombineMode::Texel0,
+        CombineMode::ModulatePrim,
+        CombineMode::ModulateEnv,
+        CombineMode::LerpEnvPrim,
+        CombineMode::ModulatePrimEnv,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CombineMode::Texel0 => "TEXEL0",
+            CombineMode::ModulatePrim => "TEXEL0 * PRIM",
+            CombineMode::ModulateEnv => "TEXEL0 * ENV",
+            CombineMode::LerpEnvPrim => "lerp(ENV, PRIM, TEXEL0)",
+            CombineMode::ModulatePrimEnv => "TEXEL0 * PRIM * ENV",
+        }
+    }
+
+    /// Whether the mode reads the primitive color.
+    pub fn uses_prim(&self) -> bool {
+        matches!(
+            self,
+            CombineMode::ModulatePrim | CombineMode::LerpEnvPrim | CombineMode::ModulatePrimEnv
+        )
+    }
+
+    /// Whether the mode reads the environment color.
+    pub fn uses_env(&self) -> bool {
+        matches!(
+            self,
+            CombineMode::ModulateEnv | CombineMode::LerpEnvPrim | CombineMode::ModulatePrimEnv
+        )
+    }
+}
+
+/// The combiner mode together with the colors it reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Combiner {
+    pub mode: CombineMode,
+    /// The primitive color, as set by `gsDPSetPrimColor`.
+    pub prim: [u8; 4],
+    /// The environment color, as set by `gsDPSetEnvColor`.
+    pub env: [u8; 4],
+}
+
+impl Default for Combiner {
+    fn default() -> Self {
+        Self {
+            mode: CombineMode::default(),
+            prim: [0xFF; 4],
+            env: [0, 0, 0, 0xFF],
+        }
+    }
+}
+
+/// Multiplies two 8-bit channels as fractions of 255.
+fn mul(a: u8, b: u8) -> u8 {
+    ((a as u32 * b as u32 + 127) / 255) as u8
+}
+
+impl Combiner {
+    /// Runs every RGBA8 pixel of `rgba` through the combiner in place.
+    pub fn apply(&self, rgba: &mut [u8]) {
+        if self.mode == CombineMode::Texel0 {
+            return;
+        }
+
+        for pixel in rgba.chunks_exact_mut(4) {
+            let texel: [u8; 4] = [pixel[0], pixel[1], pixel[2], pixel[3]];
+            let combined: [u8; 4] = match self.mode {
+                CombineMode::Texel0 => texel,
+                CombineMode::ModulatePrim => std::array::from_fn(|i| mul(texel[i], self.prim[i])),
+                CombineMode::ModulateEnv => std::array::from_fn(|i| mul(texel[i], self.env[i])),
+                CombineMode::LerpEnvPrim => std::array::from_fn(|i| {
+                    if i == 3 {
+                        mul(texel[3], self.prim[3])
+                    } else {
+                        let (env, prim) = (self.env[i] as f32, self.prim[i] as f32);
+                        (env + (prim - env) * texel[i] as f32 / 255.0).round() as u8
+                    }
+                }),
+                CombineMode::ModulatePrimEnv => {
+                    std::array::from_fn(|i| mul(mul(texel[i], self.prim[i]), self.env[i]))
+                }
+            };
+            pixel.copy_from_slice(&combined);
+        }
+    }
+
+    /// Draws the mode selector and the color pickers the mode needs.
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        egui::ComboBox::from_id_salt("combine_mode")
+            .selected_text(self.mode.name())
+            .show_ui(ui, |ui| {
+                for mode in CombineMode::ALL {
+                    ui.selectable_value(&mut self.mode, mode, mode.name());
+                }
+            });
+
+        if self.mode.uses_prim() {
+            ui.horizontal(|ui| {
+                ui.label("Primitive:");
+                ui.color_edit_button_srgba_unmultiplied(&mut self.prim);
+            });
+        }
+        if self.mode.uses_env() {
+            ui.horizontal(|ui| {
+                ui.label("Environment:");
+                ui.color_edit_button_srgba_unmultiplied(&mut self.env);
+            });
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use std::{ops::Range, path::Path};
+
+use anyhow::Result;
+
+use crate::bin_handler::BinFile;
+
+/// Differences closer together than this many bytes are reported as one
+/// range, so a re-encoded texture shows up once rather than per byte.
+pub const MERGE_GAP: usize = 16;
+
+/// The number of bytes sampled when searching for the alignment.
+const ALIGN_SAMPLES: usize = 4096;
+
+/// The largest shift tried when aligning two files, in bytes.
+pub const MAX_ALIGN_SHIFT: usize = 0x1000;
+
+/// How many shift distances are tried between progress reports.
+const ALIGN_PROGRESS_INTERVAL: usize = 64;
+
+/// A second file compared byte for byte against the open one.
+pub struct Comparison {
+    /// The file being compared against.
+    pub other: BinFile,
+    /// Offset added to a position in the open file to find the matching
+    /// byte in `other`.
+    pub shift: i64,
+    /// The differing ranges, as offsets into the open file.
+    pub ranges: Vec<Range<usize>>,
+}
+
+impl Comparison {
+    /// Opens the file to compare against.
+    ///
+    /// The ranges start out empty; diffing a large file takes a while, so
+    /// callers run `diff_ranges_with_progress` as a job and fill them in.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the other file.
+    pub fn open(path: &Path) -> Result<Self> {
+        Ok(Self {
+            other: BinFile::read(path)?,
+            shift: 0,
+            ranges: vec![],
+        })
+    }
+
+    /// The total length of the differing ranges, in bytes.
+    pub fn differing_bytes(&self) -> usize {
+        self.ranges.iter().map(|r| r.len()).sum()
+    }
+}
+
+/// Returns the ranges of `a` that differ from `b`, where `a[i]` is compared
+/// with `b[i + shift]`. Bytes of `a` with no counterpart in `b` count as
+/// different.
+///
+/// # Arguments
+/// * `a` - The open file.
+/// * `b` - The file it is compared against.
+/// * `shift` - Offset added to a position in `a` to find the byte in `b`.
+/// * `merge_gap` - Ranges separated by fewer equal bytes than this are joined.
+pub fn diff_ranges(a: &[u8], b: &[u8], shift: i64, merge_gap: usize) -> Vec<Range<usize>> {
+    diff_ranges_with_progress(a, b, shift, merge_gap, usize::MAX, |_| true)
+}
+
+/// Like `diff_ranges`, but reports the fraction of `a` compared to
+/// `progress` every `chunk_size` bytes. The comparison stops early, keeping
+/// the ranges so far, when `progress` returns `false`.
+pub fn diff_ranges_with_progress(
+    a: &[u8],
+    b: &[u8],
+    shift: i64,
+    merge_gap: usize,
+    chunk_size: usize,
+    mut progress: impl FnMut(f32) -> bool,
+) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = vec![];
+    let mut push = |range: Range<usize>| match ranges.last_mut() {
+        Some(last) if range.start - last.end < merge_gap => last.end = range.end,
+        _ => ranges.push(range),
+    };
+    let differs = |i: usize| {
+        let j = i as i64 + shift;
+        j < 0 || b.get(j as usize) != Some(&a[i])
+    };
+
+    let chunk_size = chunk_size.max(1);
+    let mut run_start = None;
+    let mut end = a.len();
+    for i in 0..a.len() {
+        if i % chunk_size == 0 && !progress(i as f32 / a.len() as f32) {
+            end = i;
+            break;
+        }
+        match (differs(i), run_start) {
+            (true, None) => run_start = Some(i),
+            (false, Some(start)) => {
+                push(start..i);
+                run_start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(start) = run_start {
+        push(start..end);
+    }
+    ranges
+}
+
+/// Finds the shift within `-max_shift..=max_shift` that makes the most
+/// sampled bytes of `a` match `b`, preferring the smallest shift on ties.
+pub fn find_alignment(a: &[u8], b: &[u8], max_shift: usize) -> i64 {
+    find_alignment_with_progress(a, b, max_shift, |_| true)
+}
+
+/// Like `find_alignment`, but reports the fraction of shifts tried to
+/// `progress` every `ALIGN_PROGRESS_INTERVAL` distances. The search stops
+/// early, returning the best shift so far, when `progress` returns `false`.
+pub fn find_alignment_with_progress(
+    a: &[u8],
+    b: &[u8],
+    max_shift: usize,
+    mut progress: impl FnMut(f32) -> bool,
+) -> i64 {
+    if a.is_empty() || b.is_empty() {
+        return 0;
+    }
+    let step = (a.len() / ALIGN_SAMPLES).max(1);
+    let samples: Vec<usize> = (0..a.len()).step_by(step).collect();
+
+    let score = |shift: i64| {
+        samples
+            .iter()
+            .filter(|&&i| {
+                let j = i as i64 + shift;
+                j >= 0 && b.get(j as usize) == Some(&a[i])
+            })
+            .count()
+    };
+
+    let mut best = (score(0), 0);
+    for distance in 1..=max_shift as i64 {
+        if distance as usize % ALIGN_PROGRESS_INTERVAL == 0
+            && !progress(distance as f32 / max_shift as f32)
+        {
+            break;
+        }
+        for shift in [-distance, distance] {
+            let s = score(shift);
+            if s > best.0 {
+                best = (s, shift);
+            }
+        }
+    }
+    best.1
+}
@@ -0,0 +1,989 @@
+use anyhow::{bail, Result};
+use pigment64::ImageType;
+
+use crate::{segments::AddressSpace, texview::decode_texture};
+
+/// Maximum number of commands executed before the interpreter gives up,
+/// guarding against display lists that loop forever.
+const MAX_COMMANDS: usize = 0x10000;
+
+/// Depth of the display list call stack.
+const MAX_DL_DEPTH: usize = 32;
+
+/// Number of entries in the vertex buffer.
+const VTX_BUFFER_SIZE: usize = 64;
+
+/// The RSP microcodes the interpreter understands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Microcode {
+    F3dex,
+    #[default]
+    F3dex2,
+}
+
+impl Microcode {
+    pub const ALL: [Microcode; 2] = [Microcode::F3dex, Microcode::F3dex2];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Microcode::F3dex => "F3DEX",
+            Microcode::F3dex2 => "F3DEX2",
+        }
+    }
+
+    /// The `G_LIGHTING` geometry mode bit.
+    pub fn lighting_bit(&self) -> u32 {
+        match self {
+            Microcode::F3dex => 0x0002_0000,
+            Microcode::F3dex2 => 0x0020_0000,
+        }
+    }
+
+    /// Decodes a single 64-bit command.
+    pub fn decode(&self, w0: u32, w1: u32) -> Command {
+        let op = (w0 >> 24) as u8;
+        if let Some(command) = decode_rdp(op, w0, w1) {
+            return command;
+        }
+
+        match self {
+            Microcode::F3dex => decode_f3dex(op, w0, w1),
+            Microcode::F3dex2 => decode_f3dex2(op, w0, w1),
+        }
+    }
+}
+
+/// A decoded display list command, normalized across microcodes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    Vertex {
+        addr: u32,
+        start: usize,
+        count: usize,
+    },
+    Triangle([usize; 3]),
+    Triangles2([usize; 3], [usize; 3]),
+    DisplayList {
+        addr: u32,
+        branch: bool,
+    },
+    EndDisplayList,
+    GeometryMode {
+        clear: u32,
+        set: u32,
+    },
+    Texture {
+        scale_s: u16,
+        scale_t: u16,
+        tile: u8,
+        on: bool,
+    },
+    Segment {
+        segment: u8,
+        addr: u32,
+    },
+    Matrix {
+        addr: u32,
+    },
+    SetTextureImage {
+        fmt: u8,
+        siz: u8,
+        width: usize,
+        addr: u32,
+    },
+    SetTile(TileDescriptor),
+    SetTileSize {
+        tile: u8,
+        uls: u16,
+        ult: u16,
+        lrs: u16,
+        lrt: u16,
+    },
+    LoadBlock {
+        tile: u8,
+        uls: u16,
+        ult: u16,
+        lrs: u16,
+        dxt: u16,
+    },
+    LoadTile {
+        tile: u8,
+        uls: u16,
+        ult: u16,
+        lrs: u16,
+        lrt: u16,
+    },
+    LoadTlut {
+        tile: u8,
+        count: usize,
+    },
+    SetPrimColor([u8; 4]),
+    SetEnvColor([u8; 4]),
+    Other(&'static str),
+    Unknown,
+}
+
+impl Command {
+    /// The GBI macro name of the command.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::Vertex { .. } => "gsSPVertex",
+            Command::Triangle(_) => "gsSP1Triangle",
+            Command::Triangles2(..) => "gsSP2Triangles",
+            Command::DisplayList { branch: false, .. } => "gsSPDisplayList",
+            Command::DisplayList { branch: true, .. } => "gsSPBranchList",
+            Command::EndDisplayList => "gsSPEndDisplayList",
+            Command::GeometryMode { .. } => "gsSPGeometryMode",
+            Command::Texture { .. } => "gsSPTexture",
+            Command::Segment { .. } => "gsSPSegment",
+            Command::Matrix { .. } => "gsSPMatrix",
+            Command::SetTextureImage { .. } => "gsDPSetTextureImage",
+            Command::SetTile(_) => "gsDPSetTile",
+            Command::SetTileSize { .. } => "gsDPSetTileSize",
+            Command::LoadBlock { .. } => "gsDPLoadBlock",
+            Command::LoadTile { .. } => "gsDPLoadTile",
+            Command::LoadTlut { .. } => "gsDPLoadTLUTCmd",
+            Command::SetPrimColor(_) => "gsDPSetPrimColor",
+            Command::SetEnvColor(_) => "gsDPSetEnvColor",
+            Command::Other(name) => name,
+            Command::Unknown => "???",
+        }
+    }
+
+    /// The address the command references, if any.
+    pub fn address(&self) -> Option<u32> {
+        match *self {
+            Command::Vertex { addr, .. }
+            | Command::DisplayList { addr, .. }
+            | Command::Segment { addr, .. }
+            | Command::Matrix { addr }
+            | Command::SetTextureImage { addr, .. } => Some(addr),
+            _ => None,
+        }
+    }
+}
+
+/// An RDP tile descriptor, as set by `G_SETTILE` and `G_SETTILESIZE`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct TileDescriptor {
+    pub tile: u8,
+    pub fmt: u8,
+    pub siz: u8,
+    /// Row size in 64-bit TMEM words.
+    pub line: u16,
+    /// TMEM address in 64-bit words.
+    pub tmem: u16,
+    pub palette: u8,
+    pub cms: u8,
+    pub cmt: u8,
+    pub masks: u8,
+    pub maskt: u8,
+    pub shifts: u8,
+    pub shiftt: u8,
+    /// Tile bounds in 10.2 fixed point.
+    pub uls: u16,
+    pub ult: u16,
+    pub lrs: u16,
+    pub lrt: u16,
+}
+
+impl TileDescriptor {
+    /// `G_TX_MIRROR` bit of `cms`/`cmt`.
+    pub const MIRROR: u8 = 0x1;
+    /// `G_TX_CLAMP` bit of `cms`/`cmt`.
+    pub const CLAMP: u8 = 0x2;
+
+    /// The tile width in texels.
+    pub fn width(&self) -> usize {
+        (self.lrs.saturating_sub(self.uls) >> 2) as usize + 1
+    }
+
+    /// The tile height in texels.
+    pub fn height(&self) -> usize {
+        (self.lrt.saturating_sub(self.ult) >> 2) as usize + 1
+    }
+}
+
+/// Everything needed to decode a texture referenced by a display list.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextureDescriptor {
+    /// Segmented address of the texel data, with `gsSPSegment` overrides
+    /// already applied.
+    pub addr: u32,
+    pub format: ImageType,
+    pub width: usize,
+    pub height: usize,
+    /// Segmented address of the palette, for CI formats.
+    pub tlut: Option<u32>,
+    /// The render tile the texture was sampled through.
+    pub tile: TileDescriptor,
+}
+
+impl TextureDescriptor {
+    /// Decodes the texture to RGBA8, reading texels and palette from `memory`.
+    pub fn decode(&self, memory: &AddressSpace) -> Vec<u8> {
+        let texels = memory.slice(self.addr).unwrap_or_default();
+        let tlut = self.tlut.and_then(|addr| memory.slice(addr));
+        decode_texture(texels, self.format, self.width, self.height, tlut)
+    }
+
+    /// Copies the raw palette out of `memory`, for CI formats.
+    pub fn tlut_bytes(&self, memory: &AddressSpace) -> Option<Vec<u8>> {
+        let len = self.format.get_size().get_tlut_size() * 2;
+        let bytes = memory.slice(self.tlut?)?;
+        Some(bytes[..len.min(bytes.len())].to_vec())
+    }
+}
+
+/// Maps an RDP `fmt`/`siz` pair to the matching image type.
+pub fn image_type_from_fmt_siz(fmt: u8, siz: u8) -> Option<ImageType> {
+    match (fmt, siz) {
+        (0, 2) => Some(ImageType::Rgba16),
+        (0, 3) => Some(ImageType::Rgba32),
+        (2, 0) => Some(ImageType::Ci4),
+        (2, 1) => Some(ImageType::Ci8),
+        (3, 0) => Some(ImageType::Ia4),
+        (3, 1) => Some(ImageType::Ia8),
+        (3, 2) => Some(ImageType::Ia16),
+        (4, 0) => Some(ImageType::I4),
+        (4, 1) => Some(ImageType::I8),
+        _ => None,
+    }
+}
+
+/// An N64 `Vtx` as stored in memory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Vtx {
+    pub pos: [i16; 3],
+    pub flag: u16,
+    /// Texture coordinates in 10.5 fixed point.
+    pub tc: [i16; 2],
+    /// Either a color or a signed normal, followed by alpha.
+    pub cn: [u8; 4],
+}
+
+impl Vtx {
+    /// Size of a `Vtx` in bytes.
+    pub const SIZE: usize = 16;
+
+    /// Reads a vertex from big-endian bytes.
+    pub fn read(bytes: &[u8]) -> Option<Self> {
+        let bytes = bytes.get(..Self::SIZE)?;
+        let s16 = |i: usize| i16::from_be_bytes([bytes[i], bytes[i + 1]]);
+
+        Some(Self {
+            pos: [s16(0), s16(2), s16(4)],
+            flag: u16::from_be_bytes([bytes[6], bytes[7]]),
+            tc: [s16(8), s16(10)],
+            cn: [bytes[12], bytes[13], bytes[14], bytes[15]],
+        })
+    }
+
+    /// The texture coordinates converted from 10.5 fixed point.
+    pub fn texcoords(&self) -> [f32; 2] {
+        [self.tc[0] as f32 / 32.0, self.tc[1] as f32 / 32.0]
+    }
+
+    /// The normal, when the vertex is used with lighting enabled.
+    pub fn normal(&self) -> [f32; 3] {
+        [
+            self.cn[0] as i8 as f32 / 127.0,
+            self.cn[1] as i8 as f32 / 127.0,
+            self.cn[2] as i8 as f32 / 127.0,
+        ]
+    }
+}
+
+/// A vertex of the mesh produced by running a display list.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeshVertex {
+    pub pos: [f32; 3],
+    /// Texture coordinates normalized to the texture size.
+    pub uv: [f32; 2],
+    pub color: [u8; 4],
+    /// Set when the vertex was loaded with lighting enabled.
+    pub normal: Option<[f32; 3]>,
+}
+
+/// A triangle of the mesh, indexing into `Mesh::vertices`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeshTriangle {
+    pub indices: [u32; 3],
+    /// Index into `Mesh::textures`, if the triangle is textured.
+    pub texture: Option<usize>,
+}
+
+/// One executed command, for listing a display list.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TracedCommand {
+    /// Segmented address of the command.
+    pub addr: u32,
+    pub w0: u32,
+    pub w1: u32,
+    pub command: Command,
+    /// Call depth at which the command ran.
+    pub depth: usize,
+}
+
+/// The result of executing a display list.
+#[derive(Debug, Clone, Default)]
+pub struct Mesh {
+    pub vertices: Vec<MeshVertex>,
+    pub triangles: Vec<MeshTriangle>,
+    pub textures: Vec<TextureDescriptor>,
+    pub trace: Vec<TracedCommand>,
+    /// Problems hit while executing, such as unresolvable addresses.
+    pub warnings: Vec<String>,
+}
+
+impl Mesh {
+    /// The axis-aligned bounds of the mesh, or `None` if it is empty.
+    pub fn bounds(&self) -> Option<([f32; 3], [f32; 3])> {
+        let first = self.vertices.first()?.pos;
+        Some(self.vertices.iter().fold((first, first), |(min, max), v| {
+            (
+                [
+                    min[0].min(v.pos[0]),
+                    min[1].min(v.pos[1]),
+                    min[2].min(v.pos[2]),
+                ],
+                [
+                    max[0].max(v.pos[0]),
+                    max[1].max(v.pos[1]),
+                    max[2].max(v.pos[2]),
+                ],
+            )
+        }))
+    }
+}
+
+/// A texture listed when it was loaded, before any triangle sampled it.
+#[derive(Debug, Clone, Copy)]
+struct UnsampledLoad {
+    /// Index into `mesh.textures`.
+    index: usize,
+    /// The TMEM word address the texture was loaded to.
+    tmem: u16,
+    /// The tile the load went through.
+    load_tile: u8,
+    /// The render tile last set up at `tmem`, which describes the texture.
+    render_tile: Option<u8>,
+    /// Whether later tile setup still describes this texture; cleared once
+    /// another load replaces it in TMEM.
+    open: bool,
+}
+
+/// Executes display lists through a simplified model of the RSP and RDP
+/// state that matters for geometry and texturing.
+pub struct Interpreter<'a> {
+    memory: &'a AddressSpace<'a>,
+    ucode: Microcode,
+    segment_overrides: [Option<u32>; 16],
+    vtx_buffer: [Option<Vtx>; VTX_BUFFER_SIZE],
+    geometry_mode: u32,
+    texture_on: bool,
+    texture_scale: [f32; 2],
+    render_tile: u8,
+    tiles: [TileDescriptor; 8],
+    timg: Option<(u8, u8, usize, u32)>,
+    /// Loads into TMEM, as (word address, segmented address, bytes per word).
+    tmem_loads: Vec<(u16, u32, u32)>,
+    /// Textures registered by a load that no triangle has sampled yet.
+    unsampled: Vec<UnsampledLoad>,
+    mesh: Mesh,
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new(memory: &'a AddressSpace<'a>, ucode: Microcode) -> Self {
+        Self {
+            memory,
+            ucode,
+            segment_overrides: [None; 16],
+            vtx_buffer: [None; VTX_BUFFER_SIZE],
+            geometry_mode: 0,
+            texture_on: false,
+            texture_scale: [1.0, 1.0],
+            render_tile: 0,
+            tiles: std::array::from_fn(|i| TileDescriptor {
+                tile: i as u8,
+                ..Default::default()
+            }),
+            timg: None,
+            tmem_loads: vec![],
+            unsampled: vec![],
+            mesh: Mesh::default(),
+        }
+    }
+
+    /// Runs the display list at `addr` and returns the resulting mesh.
+    ///
+    /// Recoverable problems are collected in `Mesh::warnings`; an error is
+    /// only returned if the first command cannot be read.
+    pub fn run(mut self, addr: u32) -> Result<Mesh> {
+        if self.read_command(addr).is_none() {
+            bail!("Display list address 0x{:08X} cannot be resolved", addr);
+        }
+
+        let mut stack: Vec<u32> = vec![];
+        let mut pc = addr;
+
+        for _ in 0..MAX_COMMANDS {
+            let Some((w0, w1)) = self.read_command(pc) else {
+                self.warn(format!("Cannot read command at 0x{:08X}", pc));
+                break;
+            };
+            let command = self.ucode.decode(w0, w1);
+            self.mesh.trace.push(TracedCommand {
+                addr: pc,
+                w0,
+                w1,
+                command,
+                depth: stack.len(),
+            });
+            pc = pc.wrapping_add(8);
+
+            match command {
+                Command::DisplayList { addr, branch } => {
+                    if !branch {
+                        if stack.len() >= MAX_DL_DEPTH {
+                            self.warn(format!("Display list stack overflow at 0x{:08X}", addr));
+                            break;
+                        }
+                        stack.push(pc);
+                    }
+                    pc = addr;
+                }
+                Command::EndDisplayList => match stack.pop() {
+                    Some(ret) => pc = ret,
+                    None => return Ok(self.mesh),
+                },
+                command => self.execute(command),
+            }
+        }
+
+        self.warn("Command limit reached before the display list ended".to_owned());
+        Ok(self.mesh)
+    }
+
+    fn warn(&mut self, message: String) {
+        self.mesh.warnings.push(message);
+    }
+
+    /// Applies `G_MW_SEGMENT` overrides, which map a segment to a physical
+    /// address that is itself resolved through segment 0.
+    fn translate(&self, addr: u32) -> u32 {
+        let segment = ((addr >> 24) & 0x0F) as usize;
+        match self.segment_overrides[segment] {
+            Some(base) if addr >> 28 == 0 => (base & 0x00FF_FFFF) + (addr & 0x00FF_FFFF),
+            _ => addr,
+        }
+    }
+
+    fn slice(&self, addr: u32) -> Option<&'a [u8]> {
+        self.memory.slice(self.translate(addr))
+    }
+
+    fn read_command(&self, addr: u32) -> Option<(u32, u32)> {
+        let bytes = self.slice(addr)?.get(..8)?;
+        Some((
+            u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]),
+        ))
+    }
+
+    fn execute(&mut self, command: Command) {
+        match command {
+            Command::Vertex { addr, start, count } => self.load_vertices(addr, start, count),
+            Command::Triangle(tri) => self.emit_triangle(tri),
+            Command::Triangles2(a, b) => {
+                self.emit_triangle(a);
+                self.emit_triangle(b);
+            }
+            Command::GeometryMode { clear, set } => {
+                self.geometry_mode = (self.geometry_mode & !clear) | set;
+            }
+            Command::Texture {
+                scale_s,
+                scale_t,
+                tile,
+                on,
+            } => {
+                self.texture_on = on;
+                self.render_tile = tile;
+                self.texture_scale = [texture_scale(scale_s), texture_scale(scale_t)];
+            }
+            Command::Segment { segment, addr } => {
+                self.segment_overrides[segment as usize & 0x0F] = Some(addr);
+            }
+            Command::SetTextureImage {
+                fmt,
+                siz,
+                width,
+                addr,
+            } => {
+                self.timg = Some((fmt, siz, width, addr));
+            }
+            Command::SetTile(desc) => {
+                let tile = &mut self.tiles[desc.tile as usize & 7];
+                *tile = TileDescriptor {
+                    uls: tile.uls,
+                    ult: tile.ult,
+                    lrs: tile.lrs,
+                    lrt: tile.lrt,
+                    ..desc
+                };
+                self.describe_loads(desc.tile);
+            }
+            Command::SetTileSize {
+                tile,
+                uls,
+                ult,
+                lrs,
+                lrt,
+            } => {
+                let desc = &mut self.tiles[tile as usize & 7];
+                desc.uls = uls;
+                desc.ult = ult;
+                desc.lrs = lrs;
+                desc.lrt = lrt;
+                self.describe_loads(tile);
+            }
+            Command::LoadBlock { tile, lrs, dxt, .. } => {
+                if let Some(addr) = self.record_load(tile, 0, 0, 8) {
+                    let texels = lrs as usize + 1;
+                    let texel_bits = 4usize << self.timg_siz();
+                    // dxt is 2048 divided by the 64-bit words per row
+                    let width = match dxt {
+                        0 => texels,
+                        dxt => 2048usize.div_ceil(dxt as usize) * 64 / texel_bits,
+                    };
+                    self.register_load(tile, addr, width, texels.div_ceil(width.max(1)));
+                }
+            }
+            Command::LoadTile {
+                tile,
+                uls,
+                ult,
+                lrs,
+                lrt,
+            } => {
+                if let Some(addr) = self.record_load(tile, uls >> 2, ult >> 2, 8) {
+                    let width = (lrs.saturating_sub(uls) >> 2) as usize + 1;
+                    let height = (lrt.saturating_sub(ult) >> 2) as usize + 1;
+                    self.register_load(tile, addr, width, height);
+                }
+            }
+            // TLUT entries are quadricated in TMEM, so each 64-bit word holds
+            // a single 16-bit color.
+            Command::LoadTlut { tile, .. } => {
+                self.record_load(tile, 0, 0, 2);
+                // Palettes are often loaded after the texture is set up
+                for load in self.unsampled.clone() {
+                    if let (true, Some(render_tile)) = (load.open, load.render_tile) {
+                        self.describe_load(load.index, self.tiles[render_tile as usize & 7]);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The texel size of the texture image, as a `G_IM_SIZ` value.
+    fn timg_siz(&self) -> u8 {
+        self.timg.map_or(2, |(_, siz, ..)| siz)
+    }
+
+    /// Remembers which image was loaded into the TMEM address of `tile`.
+    ///
+    /// Returns the segmented address the load started at.
+    fn record_load(&mut self, tile: u8, s: u16, t: u16, word_size: u32) -> Option<u32> {
+        let Some((_, siz, width, addr)) = self.timg else {
+            self.warn("Texture load without gsDPSetTextureImage".to_owned());
+            return None;
+        };
+        let addr = self.translate(addr);
+        let tmem = self.tiles[tile as usize & 7].tmem;
+        let texel_bits = 4usize << siz;
+        let offset = (t as usize * width + s as usize) * texel_bits / 8;
+
+        let addr = addr.wrapping_add(offset as u32);
+        for load in &mut self.unsampled {
+            if load.tmem == tmem {
+                load.open = false;
+            }
+        }
+        self.tmem_loads.retain(|&(t, ..)| t != tmem);
+        self.tmem_loads.push((tmem, addr, word_size));
+        Some(addr)
+    }
+
+    /// Lists a loaded texture with the mesh, so textures that no triangle
+    /// samples still show up.
+    ///
+    /// Only the load is known here, so the format and size are those of
+    /// the texture image until a render tile is set up at the same TMEM
+    /// address, and the entry is replaced by the render tile's view of the
+    /// texture once a triangle samples it.
+    fn register_load(&mut self, tile: u8, addr: u32, width: usize, height: usize) {
+        if self.mesh.textures.iter().any(|t| t.addr == addr) {
+            return;
+        }
+        let Some((fmt, siz, ..)) = self.timg else {
+            return;
+        };
+        // Block loads of 4 and 8-bit textures are usually done as 16-bit
+        let Some(format) =
+            image_type_from_fmt_siz(fmt, siz).or_else(|| image_type_from_fmt_siz(0, siz))
+        else {
+            return;
+        };
+
+        self.mesh.textures.push(TextureDescriptor {
+            addr,
+            format,
+            width,
+            height,
+            tlut: None,
+            tile: self.tiles[tile as usize & 7],
+        });
+        self.unsampled.push(UnsampledLoad {
+            index: self.mesh.textures.len() - 1,
+            tmem: self.tiles[tile as usize & 7].tmem,
+            load_tile: tile,
+            render_tile: None,
+            open: true,
+        });
+    }
+
+    /// Describes the unsampled textures at the TMEM address of `tile` by
+    /// it, after the tile was set up.
+    fn describe_loads(&mut self, tile: u8) {
+        let desc = self.tiles[tile as usize & 7];
+        for i in 0..self.unsampled.len() {
+            let load = self.unsampled[i];
+            if load.open && load.load_tile != desc.tile && load.tmem == desc.tmem {
+                self.unsampled[i].render_tile = Some(desc.tile);
+                self.describe_load(load.index, desc);
+            }
+        }
+    }
+
+    /// Replaces the format, size and palette of a listed texture with those
+    /// of the render tile it is drawn through.
+    fn describe_load(&mut self, index: usize, tile: TileDescriptor) {
+        let Some(format) = image_type_from_fmt_siz(tile.fmt, tile.siz) else {
+            return;
+        };
+        let tlut = self.tlut_for(format, &tile);
+        let texture = &mut self.mesh.textures[index];
+        *texture = TextureDescriptor {
+            addr: texture.addr,
+            format,
+            width: tile.width(),
+            height: tile.height(),
+            tlut,
+            tile,
+        };
+    }
+
+    /// The segmented address of the palette `tile` reads, for CI formats.
+    fn tlut_for(&self, format: ImageType, tile: &TileDescriptor) -> Option<u32> {
+        match format {
+            ImageType::Ci4 => self.loaded_at(256 + tile.palette as u16 * 16),
+            ImageType::Ci8 => self.loaded_at(256),
+            _ => None,
+        }
+    }
+
+    /// Finds the image loaded at a TMEM word address, accounting for
+    /// addresses that land inside a larger load.
+    fn loaded_at(&self, tmem: u16) -> Option<u32> {
+        self.tmem_loads
+            .iter()
+            .filter(|&&(t, ..)| t <= tmem)
+            .max_by_key(|&&(t, ..)| t)
+            .map(|&(t, addr, word_size)| addr.wrapping_add((tmem - t) as u32 * word_size))
+    }
+
+    fn load_vertices(&mut self, addr: u32, start: usize, count: usize) {
+        let Some(bytes) = self.slice(addr) else {
+            self.warn(format!("Cannot resolve vertices at 0x{:08X}", addr));
+            return;
+        };
+
+        for i in 0..count {
+            let Some(slot) = self.vtx_buffer.get_mut(start + i) else {
+                self.warn(format!(
+                    "Vertex load at 0x{:08X} overflows the buffer",
+                    addr
+                ));
+                return;
+            };
+            *slot = bytes.get(i * Vtx::SIZE..).and_then(Vtx::read);
+        }
+    }
+
+    /// The texture sampled by the render tile, registering it with the mesh.
+    fn current_texture(&mut self) -> Option<usize> {
+        if !self.texture_on {
+            return None;
+        }
+
+        let tile = self.tiles[self.render_tile as usize & 7];
+        let format = image_type_from_fmt_siz(tile.fmt, tile.siz)?;
+        let addr = self.loaded_at(tile.tmem)?;
+        let tlut = self.tlut_for(format, &tile);
+
+        let desc = TextureDescriptor {
+            addr,
+            format,
+            width: tile.width(),
+            height: tile.height(),
+            tlut,
+            tile,
+        };
+
+        match self.mesh.textures.iter().position(|t| *t == desc) {
+            Some(index) => Some(index),
+            None => {
+                let registered = self
+                    .unsampled
+                    .iter()
+                    .position(|load| self.mesh.textures[load.index].addr == desc.addr);
+                if let Some(pos) = registered {
+                    let index = self.unsampled.remove(pos).index;
+                    self.mesh.textures[index] = desc;
+                    return Some(index);
+                }
+                self.mesh.textures.push(desc);
+                Some(self.mesh.textures.len() - 1)
+            }
+        }
+    }
+
+    fn emit_triangle(&mut self, indices: [usize; 3]) {
+        let texture = self.current_texture();
+        let lighting = self.geometry_mode & self.ucode.lighting_bit() != 0;
+
+        let mut verts = [MeshVertex {
+            pos: [0.0; 3],
+            uv: [0.0; 2],
+            color: [0; 4],
+            normal: None,
+        }; 3];
+        for (vert, &index) in verts.iter_mut().zip(indices.iter()) {
+            let Some(vtx) = self.vtx_buffer.get(index).copied().flatten() else {
+                self.warn(format!("Triangle references unloaded vertex {}", index));
+                return;
+            };
+
+            let uv = match texture {
+                Some(t) => {
+                    let desc = &self.mesh.textures[t];
+                    let [s, t] = vtx.texcoords();
+                    [
+                        (s * self.texture_scale[0] - desc.tile.uls as f32 / 4.0)
+                            / desc.width as f32,
+                        (t * self.texture_scale[1] - desc.tile.ult as f32 / 4.0)
+                            / desc.height as f32,
+                    ]
+                }
+                None => [0.0, 0.0],
+            };
+
+            *vert = MeshVertex {
+                pos: vtx.pos.map(|p| p as f32),
+                uv,
+                color: if lighting {
+                    [0xFF, 0xFF, 0xFF, vtx.cn[3]]
+                } else {
+                    vtx.cn
+                },
+                normal: lighting.then(|| vtx.normal()),
+            };
+        }
+
+        let base = self.mesh.vertices.len() as u32;
+        self.mesh.vertices.extend_from_slice(&verts);
+        self.mesh.triangles.push(MeshTriangle {
+            indices: [base, base + 1, base + 2],
+            texture,
+        });
+    }
+}
+
+/// Converts a `G_TEXTURE` scale, where `0xFFFF` stands in for 1.0.
+fn texture_scale(scale: u16) -> f32 {
+    if scale == 0xFFFF {
+        1.0
+    } else {
+        scale as f32 / 65536.0
+    }
+}
+
+fn decode_rdp(op: u8, w0: u32, w1: u32) -> Option<Command> {
+    let command = match op {
+        0xFD => Command::SetTextureImage {
+            fmt: ((w0 >> 21) & 0x7) as u8,
+            siz: ((w0 >> 19) & 0x3) as u8,
+            width: (w0 & 0xFFF) as usize + 1,
+            addr: w1,
+        },
+        0xF5 => Command::SetTile(TileDescriptor {
+            tile: ((w1 >> 24) & 0x7) as u8,
+            fmt: ((w0 >> 21) & 0x7) as u8,
+            siz: ((w0 >> 19) & 0x3) as u8,
+            line: ((w0 >> 9) & 0x1FF) as u16,
+            tmem: (w0 & 0x1FF) as u16,
+            palette: ((w1 >> 20) & 0xF) as u8,
+            cmt: ((w1 >> 18) & 0x3) as u8,
+            maskt: ((w1 >> 14) & 0xF) as u8,
+            shiftt: ((w1 >> 10) & 0xF) as u8,
+            cms: ((w1 >> 8) & 0x3) as u8,
+            masks: ((w1 >> 4) & 0xF) as u8,
+            shifts: (w1 & 0xF) as u8,
+            ..Default::default()
+        }),
+        0xF2 => Command::SetTileSize {
+            tile: ((w1 >> 24) & 0x7) as u8,
+            uls: ((w0 >> 12) & 0xFFF) as u16,
+            ult: (w0 & 0xFFF) as u16,
+            lrs: ((w1 >> 12) & 0xFFF) as u16,
+            lrt: (w1 & 0xFFF) as u16,
+        },
+        0xF3 => Command::LoadBlock {
+            tile: ((w1 >> 24) & 0x7) as u8,
+            uls: ((w0 >> 12) & 0xFFF) as u16,
+            ult: (w0 & 0xFFF) as u16,
+            lrs: ((w1 >> 12) & 0xFFF) as u16,
+            dxt: (w1 & 0xFFF) as u16,
+        },
+        0xF4 => Command::LoadTile {
+            tile: ((w1 >> 24) & 0x7) as u8,
+            uls: ((w0 >> 12) & 0xFFF) as u16,
+            ult: (w0 & 0xFFF) as u16,
+            lrs: ((w1 >> 12) & 0xFFF) as u16,
+            lrt: (w1 & 0xFFF) as u16,
+        },
+        0xF0 => Command::LoadTlut {
+            tile: ((w1 >> 24) & 0x7) as u8,
+            count: ((w1 >> 14) & 0x3FF) as usize + 1,
+        },
+        0xFA => Command::SetPrimColor(w1.to_be_bytes()),
+        0xFB => Command::SetEnvColor(w1.to_be_bytes()),
+        0xFC => Command::Other("gsDPSetCombineLERP"),
+        0xF6 => Command::Other("gsDPFillRectangle"),
+        0xF7 => Command::Other("gsDPSetFillColor"),
+        0xF8 => Command::Other("gsDPSetFogColor"),
+        0xF9 => Command::Other("gsDPSetBlendColor"),
+        0xFE => Command::Other("gsDPSetDepthImage"),
+        0xFF => Command::Other("gsDPSetColorImage"),
+        0xE4 => Command::Other("gsSPTextureRectangle"),
+        0xE6 => Command::Other("gsDPLoadSync"),
+        0xE7 => Command::Other("gsDPPipeSync"),
+        0xE8 => Command::Other("gsDPTileSync"),
+        0xE9 => Command::Other("gsDPFullSync"),
+        0xED => Command::Other("gsDPSetScissor"),
+        0xEE => Command::Other("gsDPSetPrimDepth"),
+        0xEF => Command::Other("gsDPSetOtherMode"),
+        _ => return None,
+    };
+    Some(command)
+}
+
+fn decode_f3dex2(op: u8, w0: u32, w1: u32) -> Command {
+    let tri = |w: u32| {
+        [
+            ((w >> 16) & 0xFF) as usize / 2,
+            ((w >> 8) & 0xFF) as usize / 2,
+            (w & 0xFF) as usize / 2,
+        ]
+    };
+
+    match op {
+        0x00 => Command::Other("gsSPNoOp"),
+        0x01 => {
+            let count = ((w0 >> 12) & 0xFF) as usize;
+            let end = ((w0 >> 1) & 0x7F) as usize;
+            Command::Vertex {
+                addr: w1,
+                start: end.saturating_sub(count),
+                count,
+            }
+        }
+        0x05 => Command::Triangle(tri(w0)),
+        0x06 | 0x07 => Command::Triangles2(tri(w0), tri(w1)),
+        0xD7 => Command::Texture {
+            scale_s: (w1 >> 16) as u16,
+            scale_t: w1 as u16,
+            tile: ((w0 >> 8) & 0x7) as u8,
+            on: (w0 >> 1) & 0x7F != 0,
+        },
+        0xD8 => Command::Other("gsSPPopMatrix"),
+        0xD9 => Command::GeometryMode {
+            clear: !(w0 & 0x00FF_FFFF),
+            set: w1,
+        },
+        0xDA => Command::Matrix { addr: w1 },
+        0xDB if (w0 >> 16) & 0xFF == 6 => Command::Segment {
+            segment: ((w0 & 0xFFFF) / 4) as u8,
+            addr: w1,
+        },
+        0xDB => Command::Other("gsMoveWd"),
+        0xDC => Command::Other("gsSPMoveMem"),
+        0xDE => Command::DisplayList {
+            addr: w1,
+            branch: (w0 >> 16) & 0xFF != 0,
+        },
+        0xDF => Command::EndDisplayList,
+        0xE2 => Command::Other("gsSPSetOtherModeL"),
+        0xE3 => Command::Other("gsSPSetOtherModeH"),
+        _ => Command::Unknown,
+    }
+}
+
+fn decode_f3dex(op: u8, w0: u32, w1: u32) -> Command {
+    let tri = |w: u32| {
+        [
+            ((w >> 16) & 0xFF) as usize / 2,
+            ((w >> 8) & 0xFF) as usize / 2,
+            (w & 0xFF) as usize / 2,
+        ]
+    };
+
+    match op {
+        0x00 => Command::Other("gsSPNoOp"),
+        0x01 => Command::Matrix { addr: w1 },
+        0x03 => Command::Other("gsSPMoveMem"),
+        0x04 => Command::Vertex {
+            addr: w1,
+            start: ((w0 >> 16) & 0xFF) as usize / 2,
+            count: ((w0 >> 10) & 0x3F) as usize,
+        },
+        0x06 => Command::DisplayList {
+            addr: w1,
+            branch: (w0 >> 16) & 0xFF != 0,
+        },
+        0xB1 => Command::Triangles2(tri(w0), tri(w1)),
+        0xB6 => Command::GeometryMode { clear: w1, set: 0 },
+        0xB7 => Command::GeometryMode { clear: 0, set: w1 },
+        0xB8 => Command::EndDisplayList,
+        0xB9 => Command::Other("gsSPSetOtherModeL"),
+        0xBA => Command::Other("gsSPSetOtherModeH"),
+        0xBB => Command::Texture {
+            scale_s: (w1 >> 16) as u16,
+            scale_t: w1 as u16,
+            tile: ((w0 >> 8) & 0x7) as u8,
+            on: w0 & 0xFF != 0,
+        },
+        0xBC if w0 & 0xFF == 6 => Command::Segment {
+            segment: (((w0 >> 8) & 0xFFFF) / 4) as u8,
+            addr: w1,
+        },
+        0xBC => Command::Other("gsMoveWd"),
+        0xBD => Command::Other("gsSPPopMatrix"),
+        0xBF => Command::Triangle(tri(w1)),
+        0xC0 => Command::Other("gsSPNoOp"),
+        _ => Command::Unknown,
+    }
+}
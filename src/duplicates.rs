@@ -0,0 +1,180 @@
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+};
+
+use pigment64::ImageType;
+
+use crate::{display_list::TextureDescriptor, segments::AddressSpace, texview::image_byte_size};
+
+/// The number of textures grouped between progress reports.
+const PROGRESS_INTERVAL: usize = 256;
+
+/// A texture region read out of memory, ready to be compared.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextureData {
+    pub format: ImageType,
+    pub width: usize,
+    pub height: usize,
+    /// The encoded texels, followed by the palette for CI formats.
+    pub raw: Vec<u8>,
+    /// The decoded RGBA8 pixels.
+    pub rgba: Vec<u8>,
+}
+
+impl TextureData {
+    /// Reads a texture and its palette from `memory`.
+    ///
+    /// # Arguments
+    /// * `desc` - The texture to read.
+    /// * `memory` - Where the texels and palette are read from.
+    /// * `decode` - Whether to decode `rgba`, which only near matching
+    ///   needs; it is left empty otherwise.
+    pub fn read(desc: &TextureDescriptor, memory: &AddressSpace, decode: bool) -> Self {
+        let len = image_byte_size(desc.format, desc.width, desc.height);
+        let texels = memory.slice(desc.addr).unwrap_or_default();
+        let mut raw = texels[..len.min(texels.len())].to_vec();
+        raw.extend(desc.tlut_bytes(memory).unwrap_or_default());
+
+        Self {
+            format: desc.format,
+            width: desc.width,
+            height: desc.height,
+            raw,
+            rgba: if decode { desc.decode(memory) } else { vec![] },
+        }
+    }
+
+    /// Whether the texture is a single repeated byte, such as padding,
+    /// which matches every other fill of the same size without being a
+    /// texture.
+    pub fn is_fill(&self) -> bool {
+        self.raw.windows(2).all(|w| w[0] == w[1])
+    }
+
+    fn key(&self) -> (ImageType, usize, usize, u64) {
+        let mut hasher = DefaultHasher::new();
+        self.raw.hash(&mut hasher);
+        (self.format, self.width, self.height, hasher.finish())
+    }
+
+    /// Whether the decoded pixels are within `tolerance` of `other`'s in
+    /// every channel.
+    fn near(&self, other: &TextureData, tolerance: u8) -> bool {
+        (self.width, self.height) == (other.width, other.height)
+            && !self.rgba.is_empty()
+            && self.rgba.len() == other.rgba.len()
+            && self
+                .rgba
+                .iter()
+                .zip(&other.rgba)
+                .all(|(&x, &y)| x.abs_diff(y) <= tolerance)
+    }
+}
+
+/// Returns the indices of the descriptors that refer to distinct regions,
+/// keeping the first of each. Display lists often load the same texture
+/// several times, which is not duplication.
+pub fn distinct_regions(textures: &[TextureDescriptor]) -> Vec<usize> {
+    let mut seen = vec![];
+    (0..textures.len())
+        .filter(|&i| {
+            let t = &textures[i];
+            let region = (t.addr, t.format, t.width, t.height, t.tlut);
+            if seen.contains(&region) {
+                false
+            } else {
+                seen.push(region);
+                true
+            }
+        })
+        .collect()
+}
+
+/// Splits a file into back to back textures of one format and size, so
+/// that copies stored anywhere in it can be found.
+///
+/// # Arguments
+/// * `len` - The length of the file in bytes.
+/// * `template` - The format, size and palette of every region; its
+///   address is ignored.
+/// * `addr_of` - The address that reads a file offset, or `None` if no
+///   address does.
+pub fn file_regions(
+    len: usize,
+    template: &TextureDescriptor,
+    addr_of: impl Fn(usize) -> Option<u32>,
+) -> Vec<TextureDescriptor> {
+    let size = image_byte_size(template.format, template.width, template.height);
+    if size == 0 {
+        return vec![];
+    }
+    (0..len.saturating_sub(size - 1))
+        .step_by(size)
+        .filter_map(|offset| {
+            Some(TextureDescriptor {
+                addr: addr_of(offset)?,
+                ..template.clone()
+            })
+        })
+        .collect()
+}
+
+/// Groups textures with identical contents.
+///
+/// Textures match exactly when format, size, texels and palette are the
+/// same. With a tolerance, textures of the same size whose decoded pixels
+/// differ by at most `tolerance` in every channel from the first member of
+/// a group join it as well, which catches copies re-encoded to another
+/// format.
+///
+/// Returns the groups with more than one member, as indices into
+/// `textures`, in the order their first member appears.
+pub fn group_duplicates(textures: &[TextureData], tolerance: Option<u8>) -> Vec<Vec<usize>> {
+    group_duplicates_with_progress(textures, tolerance, |_| true)
+}
+
+/// Like `group_duplicates`, but reports the fraction of textures grouped to
+/// `progress` every `PROGRESS_INTERVAL` textures. Grouping stops early,
+/// keeping the groups so far, when `progress` returns `false`.
+pub fn group_duplicates_with_progress(
+    textures: &[TextureData],
+    tolerance: Option<u8>,
+    mut progress: impl FnMut(f32) -> bool,
+) -> Vec<Vec<usize>> {
+    let mut groups: Vec<Vec<usize>> = vec![];
+    // Every texture by its hash, with the group it is in; textures whose
+    // hashes collide are told apart by their bytes
+    let mut exact: HashMap<_, Vec<(usize, usize)>> = HashMap::new();
+    for (i, texture) in textures.iter().enumerate() {
+        if i % PROGRESS_INTERVAL == 0 && !progress(i as f32 / textures.len() as f32) {
+            break;
+        }
+        let bucket = exact.entry(texture.key()).or_default();
+        let same = bucket
+            .iter()
+            .find(|&&(other, _)| textures[other].raw == texture.raw)
+            .map(|&(_, group)| group);
+        // Near matches are measured against the first member of a group,
+        // so a chain of small differences does not join distant textures
+        let group = same.or_else(|| {
+            let tolerance = tolerance?;
+            groups
+                .iter()
+                .position(|group| textures[group[0]].near(texture, tolerance))
+        });
+        let group = match group {
+            Some(group) => {
+                groups[group].push(i);
+                group
+            }
+            None => {
+                groups.push(vec![i]);
+                groups.len() - 1
+            }
+        };
+        bucket.push((i, group));
+    }
+    groups.retain(|g| g.len() > 1);
+    groups
+}
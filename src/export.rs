@@ -0,0 +1,454 @@
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use gltf::{
+    binary::Glb,
+    json::{
+        self,
+        accessor::{ComponentType, GenericComponentType},
+        texture::WrappingMode,
+        validation::Checked::Valid,
+    },
+};
+
+use crate::{
+    display_list::{Mesh, MeshTriangle, TextureDescriptor, TileDescriptor},
+    segments::AddressSpace,
+};
+
+/// A texture decoded for export.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedTexture {
+    pub width: usize,
+    pub height: usize,
+    /// RGBA8 pixels.
+    pub rgba: Vec<u8>,
+    pub tile: TileDescriptor,
+}
+
+/// The file formats a mesh can be exported to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModelFormat {
+    /// Binary glTF 2.0 with embedded textures.
+    Glb,
+    /// Wavefront OBJ with an MTL file and PNG textures.
+    Obj,
+}
+
+impl ModelFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ModelFormat::Glb => "glTF Binary",
+            ModelFormat::Obj => "Wavefront OBJ",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ModelFormat::Glb => "glb",
+            ModelFormat::Obj => "obj",
+        }
+    }
+
+    /// Writes a mesh and its textures in this format.
+    pub fn write(&self, path: &Path, mesh: &Mesh, textures: &[DecodedTexture]) -> Result<()> {
+        match self {
+            ModelFormat::Glb => write_glb(path, mesh, textures),
+            ModelFormat::Obj => write_obj(path, mesh, textures),
+        }
+    }
+}
+
+/// Decodes every texture a mesh references.
+pub fn decode_textures(memory: &AddressSpace, mesh: &Mesh) -> Vec<DecodedTexture> {
+    mesh.textures
+        .iter()
+        .map(|desc| decode_texture(memory, desc))
+        .collect()
+}
+
+/// Decodes one texture of a mesh.
+pub fn decode_texture(memory: &AddressSpace, desc: &TextureDescriptor) -> DecodedTexture {
+    DecodedTexture {
+        width: desc.width,
+        height: desc.height,
+        rgba: desc.decode(memory),
+        tile: desc.tile,
+    }
+}
+
+/// Encodes RGBA8 pixels as a PNG.
+pub fn encode_png(width: usize, height: usize, rgba: &[u8]) -> Result<Vec<u8>> {
+    let mut out = vec![];
+    let mut encoder = png::Encoder::new(&mut out, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(rgba)?;
+    Ok(out)
+}
+
+/// Groups triangles by texture so each group becomes one primitive or
+/// material, keeping the order textures first appear in.
+pub fn group_by_texture(triangles: &[MeshTriangle]) -> Vec<(Option<usize>, Vec<&MeshTriangle>)> {
+    let mut groups: Vec<(Option<usize>, Vec<&MeshTriangle>)> = vec![];
+    for tri in triangles {
+        match groups
+            .iter_mut()
+            .find(|(texture, _)| *texture == tri.texture)
+        {
+            Some((_, tris)) => tris.push(tri),
+            None => groups.push((tri.texture, vec![tri])),
+        }
+    }
+    groups
+}
+
+/// Writes a mesh as Wavefront OBJ, with an MTL file and one PNG per texture
+/// next to it.
+///
+/// # Arguments
+/// * `path` - The path of the `.obj` file.
+/// * `mesh` - The mesh to write.
+/// * `textures` - The decoded textures, indexed like `mesh.textures`.
+pub fn write_obj(path: &Path, mesh: &Mesh, textures: &[DecodedTexture]) -> Result<()> {
+    let stem = path
+        .file_stem()
+        .context("Export path has no file name")?
+        .to_string_lossy()
+        .into_owned();
+    let sibling = |name: String| -> PathBuf { path.with_file_name(name) };
+
+    let mut mtl = String::new();
+    for (i, texture) in textures.iter().enumerate() {
+        let png_name = format!("{}_tex{}.png", stem, i);
+        fs::write(
+            sibling(png_name.clone()),
+            encode_png(texture.width, texture.height, &texture.rgba)?,
+        )?;
+        writeln!(mtl, "newmtl tex{}\nKd 1 1 1\nmap_Kd {}\n", i, png_name)?;
+    }
+    writeln!(mtl, "newmtl untextured\nKd 1 1 1")?;
+    fs::write(sibling(format!("{}.mtl", stem)), mtl)?;
+
+    let mut obj = format!("mtllib {}.mtl\no {}\n", stem, stem);
+    for v in &mesh.vertices {
+        let [r, g, b, _] = v.color.map(|c| c as f32 / 255.0);
+        writeln!(
+            obj,
+            "v {} {} {} {:.4} {:.4} {:.4}",
+            v.pos[0], v.pos[1], v.pos[2], r, g, b
+        )?;
+    }
+    for v in &mesh.vertices {
+        // OBJ texture coordinates start at the bottom of the image.
+        writeln!(obj, "vt {} {}", v.uv[0], 1.0 - v.uv[1])?;
+    }
+    let has_normals = mesh.vertices.iter().any(|v| v.normal.is_some());
+    if has_normals {
+        for v in &mesh.vertices {
+            let [x, y, z] = v.normal.unwrap_or([0.0, 1.0, 0.0]);
+            writeln!(obj, "vn {} {} {}", x, y, z)?;
+        }
+    }
+
+    for (texture, tris) in group_by_texture(&mesh.triangles) {
+        match texture {
+            Some(t) => writeln!(obj, "usemtl tex{}", t)?,
+            None => writeln!(obj, "usemtl untextured")?,
+        }
+        for tri in tris {
+            let [a, b, c] = tri.indices.map(|i| i + 1);
+            if has_normals {
+                writeln!(obj, "f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}")?;
+            } else {
+                writeln!(obj, "f {a}/{a} {b}/{b} {c}/{c}")?;
+            }
+        }
+    }
+    fs::write(path, obj)?;
+
+    Ok(())
+}
+
+/// Accumulates the binary chunk and the glTF document that indexes into it.
+#[derive(Default)]
+struct GlbBuilder {
+    bin: Vec<u8>,
+    root: json::Root,
+}
+
+impl GlbBuilder {
+    /// Appends bytes as a new buffer view and returns its index.
+    fn push_view(
+        &mut self,
+        bytes: &[u8],
+        target: Option<json::buffer::Target>,
+    ) -> json::Index<json::buffer::View> {
+        while self.bin.len() % 4 != 0 {
+            self.bin.push(0);
+        }
+        let view = json::buffer::View {
+            buffer: json::Index::new(0),
+            byte_length: bytes.len().into(),
+            byte_offset: Some(self.bin.len().into()),
+            byte_stride: None,
+            extensions: Default::default(),
+            extras: Default::default(),
+            name: None,
+            target: target.map(Valid),
+        };
+        self.bin.extend_from_slice(bytes);
+        self.root.push(view)
+    }
+
+    /// Appends an accessor over a new buffer view and returns its index.
+    fn push_accessor(
+        &mut self,
+        bytes: &[u8],
+        component_type: ComponentType,
+        count: usize,
+        type_: json::accessor::Type,
+        target: json::buffer::Target,
+    ) -> json::Index<json::Accessor> {
+        let view = self.push_view(bytes, Some(target));
+        self.root.push(json::Accessor {
+            buffer_view: Some(view),
+            byte_offset: None,
+            count: count.into(),
+            component_type: Valid(GenericComponentType(component_type)),
+            extensions: Default::default(),
+            extras: Default::default(),
+            type_: Valid(type_),
+            min: None,
+            max: None,
+            name: None,
+            normalized: false,
+            sparse: None,
+        })
+    }
+}
+
+fn gl_wrap(mode: u8) -> WrappingMode {
+    if mode & TileDescriptor::CLAMP != 0 {
+        WrappingMode::ClampToEdge
+    } else if mode & TileDescriptor::MIRROR != 0 {
+        WrappingMode::MirroredRepeat
+    } else {
+        WrappingMode::Repeat
+    }
+}
+
+/// Serializes a mesh as a binary glTF 2.0 (`.glb`) file with embedded
+/// PNG textures.
+pub fn mesh_to_glb(mesh: &Mesh, textures: &[DecodedTexture]) -> Result<Vec<u8>> {
+    use json::{accessor::Type, buffer::Target, mesh::Semantic};
+
+    let mut glb = GlbBuilder::default();
+    glb.root.asset.generator = Some(format!("motex {}", env!("CARGO_PKG_VERSION")));
+
+    let mut materials = vec![];
+    for (i, texture) in textures.iter().enumerate() {
+        let png = encode_png(texture.width, texture.height, &texture.rgba)?;
+        let view = glb.push_view(&png, None);
+        let image = glb.root.push(json::Image {
+            buffer_view: Some(view),
+            mime_type: Some(json::image::MimeType("image/png".to_owned())),
+            name: None,
+            uri: None,
+            extensions: Default::default(),
+            extras: Default::default(),
+        });
+        let sampler = glb.root.push(json::texture::Sampler {
+            mag_filter: Some(Valid(json::texture::MagFilter::Nearest)),
+            min_filter: Some(Valid(json::texture::MinFilter::Nearest)),
+            wrap_s: Valid(gl_wrap(texture.tile.cms)),
+            wrap_t: Valid(gl_wrap(texture.tile.cmt)),
+            ..Default::default()
+        });
+        let texture = glb.root.push(json::Texture {
+            name: None,
+            sampler: Some(sampler),
+            source: image,
+            extensions: Default::default(),
+            extras: Default::default(),
+        });
+        materials.push(glb.root.push(json::Material {
+            name: Some(format!("tex{}", i)),
+            alpha_mode: Valid(json::material::AlphaMode::Mask),
+            pbr_metallic_roughness: json::material::PbrMetallicRoughness {
+                base_color_texture: Some(json::texture::Info {
+                    index: texture,
+                    tex_coord: 0,
+                    extensions: Default::default(),
+                    extras: Default::default(),
+                }),
+                metallic_factor: json::material::StrengthFactor(0.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        }));
+    }
+    let untextured_material = glb.root.push(json::Material {
+        name: Some("untextured".to_owned()),
+        pbr_metallic_roughness: json::material::PbrMetallicRoughness {
+            metallic_factor: json::material::StrengthFactor(0.0),
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+
+    let mut primitives = vec![];
+    for (texture, tris) in group_by_texture(&mesh.triangles) {
+        let vertices: Vec<_> = tris
+            .iter()
+            .flat_map(|tri| tri.indices.map(|i| &mesh.vertices[i as usize]))
+            .collect();
+        let count = vertices.len();
+        let mut attributes = BTreeMap::new();
+
+        let positions: Vec<u8> = vertices
+            .iter()
+            .flat_map(|v| v.pos)
+            .flat_map(f32::to_le_bytes)
+            .collect();
+        let (min, max) = vertices
+            .iter()
+            .fold(([f32::MAX; 3], [f32::MIN; 3]), |(min, max), v| {
+                (
+                    std::array::from_fn(|i| min[i].min(v.pos[i])),
+                    std::array::from_fn(|i| max[i].max(v.pos[i])),
+                )
+            });
+        let position = glb.push_accessor(
+            &positions,
+            ComponentType::F32,
+            count,
+            Type::Vec3,
+            Target::ArrayBuffer,
+        );
+        let accessor = &mut glb.root.accessors[position.value()];
+        accessor.min = Some(json::Value::from(min.to_vec()));
+        accessor.max = Some(json::Value::from(max.to_vec()));
+        attributes.insert(Valid(Semantic::Positions), position);
+
+        let uvs: Vec<u8> = vertices
+            .iter()
+            .flat_map(|v| v.uv)
+            .flat_map(f32::to_le_bytes)
+            .collect();
+        let uv = glb.push_accessor(
+            &uvs,
+            ComponentType::F32,
+            count,
+            Type::Vec2,
+            Target::ArrayBuffer,
+        );
+        attributes.insert(Valid(Semantic::TexCoords(0)), uv);
+
+        let colors: Vec<u8> = vertices.iter().flat_map(|v| v.color).collect();
+        let color = glb.push_accessor(
+            &colors,
+            ComponentType::U8,
+            count,
+            Type::Vec4,
+            Target::ArrayBuffer,
+        );
+        glb.root.accessors[color.value()].normalized = true;
+        attributes.insert(Valid(Semantic::Colors(0)), color);
+
+        if vertices.iter().all(|v| v.normal.is_some()) {
+            let normals: Vec<u8> = vertices
+                .iter()
+                .flat_map(|v| {
+                    let [x, y, z] = v.normal.unwrap_or_default();
+                    let len = (x * x + y * y + z * z).sqrt().max(f32::EPSILON);
+                    [x / len, y / len, z / len]
+                })
+                .flat_map(f32::to_le_bytes)
+                .collect();
+            let normal = glb.push_accessor(
+                &normals,
+                ComponentType::F32,
+                count,
+                Type::Vec3,
+                Target::ArrayBuffer,
+            );
+            attributes.insert(Valid(Semantic::Normals), normal);
+        }
+
+        let indices: Vec<u8> = (0..count as u32).flat_map(u32::to_le_bytes).collect();
+        let index = glb.push_accessor(
+            &indices,
+            ComponentType::U32,
+            count,
+            Type::Scalar,
+            Target::ElementArrayBuffer,
+        );
+
+        primitives.push(json::mesh::Primitive {
+            attributes,
+            extensions: Default::default(),
+            extras: Default::default(),
+            indices: Some(index),
+            material: Some(texture.map_or(untextured_material, |t| materials[t])),
+            mode: Valid(json::mesh::Mode::Triangles),
+            targets: None,
+        });
+    }
+    while glb.bin.len() % 4 != 0 {
+        glb.bin.push(0);
+    }
+
+    let gltf_mesh = glb.root.push(json::Mesh {
+        extensions: Default::default(),
+        extras: Default::default(),
+        name: None,
+        primitives,
+        weights: None,
+    });
+    let node = glb.root.push(json::Node {
+        mesh: Some(gltf_mesh),
+        ..Default::default()
+    });
+    let scene = glb.root.push(json::Scene {
+        extensions: Default::default(),
+        extras: Default::default(),
+        name: None,
+        nodes: vec![node],
+    });
+    glb.root.scene = Some(scene);
+    glb.root.push(json::Buffer {
+        byte_length: glb.bin.len().into(),
+        extensions: Default::default(),
+        extras: Default::default(),
+        name: None,
+        uri: None,
+    });
+
+    let json = glb.root.to_vec()?;
+    let out = Glb {
+        header: gltf::binary::Header {
+            magic: *b"glTF",
+            version: 2,
+            // Filled in by the writer
+            length: 0,
+        },
+        json: Cow::Owned(json),
+        bin: Some(Cow::Owned(glb.bin)),
+    }
+    .to_vec()?;
+
+    Ok(out)
+}
+
+/// Writes a mesh as a binary glTF 2.0 file.
+pub fn write_glb(path: &Path, mesh: &Mesh, textures: &[DecodedTexture]) -> Result<()> {
+    fs::write(path, mesh_to_glb(mesh, textures)?)?;
+    Ok(())
+}
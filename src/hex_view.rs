@@ -0,0 +1,102 @@
+use std::ops::Range;
+
+use eframe::egui::{self, text::LayoutJob, Color32, FontId, TextFormat};
+
+/// Number of bytes shown per row.
+pub const BYTES_PER_ROW: usize = 16;
+
+/// Returns the row-aligned offset to start a dump of `rows` rows at, so
+/// that it begins at `start` unless that would leave `highlight` out of
+/// view.
+pub fn visible_start(start: usize, rows: usize, highlight: Option<&Range<usize>>) -> usize {
+    let start = start - start % BYTES_PER_ROW;
+    let Some(highlight) = highlight.filter(|h| !h.is_empty()) else {
+        return start;
+    };
+    let end = start + rows.max(1) * BYTES_PER_ROW;
+    if highlight.start >= start && highlight.end <= end {
+        start
+    } else {
+        highlight.start - highlight.start % BYTES_PER_ROW
+    }
+}
+
+/// Draws a hex dump of `data` with an ASCII column.
+///
+/// # Arguments
+/// * `ui` - The egui ui to draw into.
+/// * `data` - The data to dump.
+/// * `start` - The offset of the first byte to show; rounded down to a row.
+/// * `rows` - The number of rows to show.
+/// * `highlight` - A byte range to draw with a highlighted background.
+/// * `base` - The address shown for the first byte of `data`.
+pub fn hex_dump(
+    ui: &mut egui::Ui,
+    data: &[u8],
+    start: usize,
+    rows: usize,
+    highlight: Option<&Range<usize>>,
+    base: u32,
+) {
+    let font = FontId::monospace(12.0);
+    let text_color = ui.visuals().text_color();
+    let weak_color = ui.visuals().weak_text_color();
+    let highlight_color = ui.visuals().selection.bg_fill;
+
+    let start = start - start % BYTES_PER_ROW;
+    for row in 0..rows {
+        let row_start = start + row * BYTES_PER_ROW;
+        if row_start >= data.len() {
+            break;
+        }
+        let row_bytes = &data[row_start..(row_start + BYTES_PER_ROW).min(data.len())];
+
+        let mut job = LayoutJob::default();
+        let format = |color: Color32, background: Color32| TextFormat {
+            font_id: font.clone(),
+            color,
+            background,
+            ..Default::default()
+        };
+
+        job.append(
+            &format!("{:08X}  ", base.wrapping_add(row_start as u32)),
+            0.0,
+            format(weak_color, Color32::TRANSPARENT),
+        );
+
+        for (i, byte) in row_bytes.iter().enumerate() {
+            let highlighted = highlight.is_some_and(|h| h.contains(&(row_start + i)));
+            let background = if highlighted {
+                highlight_color
+            } else {
+                Color32::TRANSPARENT
+            };
+            job.append(
+                &format!("{:02X}", byte),
+                0.0,
+                format(text_color, background),
+            );
+            job.append(" ", 0.0, format(text_color, Color32::TRANSPARENT));
+        }
+
+        job.append(
+            &" ".repeat((BYTES_PER_ROW - row_bytes.len()) * 3 + 1),
+            0.0,
+            format(text_color, Color32::TRANSPARENT),
+        );
+        let ascii: String = row_bytes
+            .iter()
+            .map(|&b| {
+                if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        job.append(&ascii, 0.0, format(weak_color, Color32::TRANSPARENT));
+
+        ui.label(job);
+    }
+}
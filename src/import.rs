@@ -0,0 +1,815 @@
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use pigment64::color::Color;
+
+use crate::{
+    display_list::{Mesh, MeshTriangle, MeshVertex, Vtx},
+    export::group_by_texture,
+};
+
+/// Number of vertices loaded per `gsSPVertex`, the size of the F3DEX2
+/// vertex cache.
+pub const VTX_CACHE_SIZE: usize = 32;
+
+/// Largest RGBA16 texture that fits in TMEM with a single `gsDPLoadBlock`.
+pub const MAX_TEXELS: usize = 2048;
+
+/// The number of bytes a segmented address can reach, through its 24-bit
+/// offset.
+pub const SEGMENT_SIZE: usize = 0x100_0000;
+
+/// A texture loaded alongside an imported mesh.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedTexture {
+    pub name: String,
+    pub width: usize,
+    pub height: usize,
+    /// RGBA8 pixels.
+    pub rgba: Vec<u8>,
+}
+
+/// A mesh loaded from a model file. Triangle texture indices refer to
+/// `textures`; `mesh.textures` is left empty.
+#[derive(Debug, Clone, Default)]
+pub struct ImportedModel {
+    pub mesh: Mesh,
+    pub textures: Vec<ImportedTexture>,
+}
+
+impl ImportedModel {
+    /// The scale that fits the model into the range of an `s16` with room
+    /// to spare.
+    pub fn auto_scale(&self) -> f32 {
+        let extent = self
+            .mesh
+            .bounds()
+            .map(|(min, max)| {
+                min.iter()
+                    .chain(max.iter())
+                    .fold(0.0f32, |acc, v| acc.max(v.abs()))
+            })
+            .unwrap_or(0.0);
+        if extent > 0.0 {
+            1000.0 / extent
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Loads an OBJ or glTF (`.gltf`/`.glb`) model, picking the loader by
+/// file extension.
+pub fn load_model(path: &Path) -> Result<ImportedModel> {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "obj" => load_obj(path),
+        "gltf" | "glb" => load_gltf(path),
+        _ => bail!("Unsupported model format: {}", path.display()),
+    }
+}
+
+/// Reads a PNG file as RGBA8.
+pub fn load_png(path: &Path) -> Result<(usize, usize, Vec<u8>)> {
+    let mut decoder = png::Decoder::new(fs::File::open(path)?);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf)?;
+    let pixels = &buf[..info.buffer_size()];
+
+    let rgba = match info.color_type {
+        png::ColorType::Rgba => pixels.to_vec(),
+        png::ColorType::Rgb => pixels
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 0xFF])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => pixels
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        png::ColorType::Grayscale => pixels.iter().flat_map(|&p| [p, p, p, 0xFF]).collect(),
+        png::ColorType::Indexed => bail!("Indexed PNG was not expanded"),
+    };
+
+    Ok((info.width as usize, info.height as usize, rgba))
+}
+
+/// Loads a Wavefront OBJ file, along with the `map_Kd` textures of its
+/// materials. Polygons are triangulated as fans.
+pub fn load_obj(path: &Path) -> Result<ImportedModel> {
+    let text = fs::read_to_string(path)?;
+    let dir = path.parent().unwrap_or(Path::new("."));
+
+    let mut positions: Vec<[f32; 3]> = vec![];
+    let mut colors: Vec<[u8; 4]> = vec![];
+    let mut uvs: Vec<[f32; 2]> = vec![];
+    let mut normals: Vec<[f32; 3]> = vec![];
+    let mut materials: HashMap<String, PathBuf> = HashMap::new();
+    let mut texture_paths: Vec<PathBuf> = vec![];
+    let mut current_texture = None;
+    let mut model = ImportedModel::default();
+
+    let floats = |parts: &[&str]| -> Result<Vec<f32>> {
+        parts
+            .iter()
+            .map(|p| p.parse::<f32>().context("Invalid number in OBJ"))
+            .collect()
+    };
+
+    for line in text.lines() {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let Some((&keyword, args)) = parts.split_first() else {
+            continue;
+        };
+
+        match keyword {
+            "v" => {
+                let v = floats(args)?;
+                if v.len() < 3 {
+                    bail!("Vertex with fewer than 3 coordinates: {}", line);
+                }
+                positions.push([v[0], v[1], v[2]]);
+                // Some exporters append vertex colors after the position.
+                let [r, g, b] = match v.len() {
+                    6.. => [v[3], v[4], v[5]].map(|c| (c.clamp(0.0, 1.0) * 255.0) as u8),
+                    _ => [0xFF; 3],
+                };
+                colors.push([r, g, b, 0xFF]);
+            }
+            "vt" => {
+                let v = floats(args)?;
+                // OBJ texture coordinates start at the bottom of the image.
+                uvs.push([
+                    v.first().copied().unwrap_or(0.0),
+                    1.0 - v.get(1).copied().unwrap_or(0.0),
+                ]);
+            }
+            "vn" => {
+                let v = floats(args)?;
+                if v.len() < 3 {
+                    bail!("Normal with fewer than 3 components: {}", line);
+                }
+                normals.push([v[0], v[1], v[2]]);
+            }
+            "mtllib" => {
+                let mtl_path = dir.join(args.join(" "));
+                if let Ok(mtl) = fs::read_to_string(&mtl_path) {
+                    parse_mtl(&mtl, dir, &mut materials);
+                }
+            }
+            "usemtl" => {
+                current_texture = materials.get(&args.join(" ")).map(|texture_path| {
+                    match texture_paths.iter().position(|p| p == texture_path) {
+                        Some(i) => i,
+                        None => {
+                            texture_paths.push(texture_path.clone());
+                            texture_paths.len() - 1
+                        }
+                    }
+                });
+            }
+            "f" => {
+                let corners = args
+                    .iter()
+                    .map(|corner| {
+                        obj_vertex(corner, &positions, &colors, &uvs, &normals)
+                            .with_context(|| format!("Invalid face: {}", line))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+
+                for i in 1..corners.len().saturating_sub(1) {
+                    let base = model.mesh.vertices.len() as u32;
+                    model
+                        .mesh
+                        .vertices
+                        .extend([corners[0], corners[i], corners[i + 1]]);
+                    model.mesh.triangles.push(MeshTriangle {
+                        indices: [base, base + 1, base + 2],
+                        texture: current_texture,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for texture_path in texture_paths {
+        let (width, height, rgba) = load_png(&texture_path)
+            .with_context(|| format!("Failed to load texture {}", texture_path.display()))?;
+        model.textures.push(ImportedTexture {
+            name: texture_name(&texture_path),
+            width,
+            height,
+            rgba,
+        });
+    }
+
+    Ok(model)
+}
+
+/// Collects `map_Kd` textures by material name.
+fn parse_mtl(mtl: &str, dir: &Path, materials: &mut HashMap<String, PathBuf>) {
+    let mut current = None;
+    for line in mtl.lines() {
+        let line = line.trim();
+        if let Some(name) = line.strip_prefix("newmtl ") {
+            current = Some(name.trim().to_owned());
+        } else if let Some(texture) = line.strip_prefix("map_Kd ") {
+            if let Some(name) = &current {
+                materials.insert(name.clone(), dir.join(texture.trim()));
+            }
+        }
+    }
+}
+
+/// Resolves one `v/vt/vn` face corner. OBJ indices are 1-based and may be
+/// negative to count back from the end.
+fn obj_vertex(
+    corner: &str,
+    positions: &[[f32; 3]],
+    colors: &[[u8; 4]],
+    uvs: &[[f32; 2]],
+    normals: &[[f32; 3]],
+) -> Option<MeshVertex> {
+    let index = |field: Option<&str>, len: usize| -> Option<Option<usize>> {
+        match field {
+            None | Some("") => Some(None),
+            Some(field) => {
+                let i: isize = field.parse().ok()?;
+                let i = if i < 0 { len as isize + i } else { i - 1 };
+                (0..len as isize).contains(&i).then_some(Some(i as usize))
+            }
+        }
+    };
+
+    let mut fields = corner.split('/');
+    let v = index(fields.next(), positions.len())??;
+    let vt = index(fields.next(), uvs.len())?;
+    let vn = index(fields.next(), normals.len())?;
+
+    Some(MeshVertex {
+        pos: positions[v],
+        uv: vt.map(|i| uvs[i]).unwrap_or_default(),
+        color: colors[v],
+        normal: vn.map(|i| normals[i]),
+    })
+}
+
+fn texture_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "texture".to_owned())
+}
+
+/// Loads every mesh in the default scene of a glTF file, applying node
+/// transforms. Each material's base color texture becomes one texture.
+pub fn load_gltf(path: &Path) -> Result<ImportedModel> {
+    let (document, buffers, images) = gltf::import(path)?;
+    let mut model = ImportedModel::default();
+    let mut image_textures: HashMap<usize, usize> = HashMap::new();
+
+    let scene = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .context("glTF file has no scenes")?;
+
+    let mut stack: Vec<(gltf::Node, [[f32; 4]; 4])> =
+        scene.nodes().map(|node| (node, IDENTITY)).collect();
+    while let Some((node, parent)) = stack.pop() {
+        let transform = mat_mul(&parent, &node.transform().matrix());
+        stack.extend(node.children().map(|child| (child, transform)));
+
+        let Some(mesh) = node.mesh() else {
+            continue;
+        };
+        for primitive in mesh.primitives() {
+            if primitive.mode() != gltf::mesh::Mode::Triangles {
+                continue;
+            }
+            let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+            let Some(positions) = reader.read_positions() else {
+                continue;
+            };
+            let positions: Vec<[f32; 3]> =
+                positions.map(|p| transform_point(&transform, p)).collect();
+            let uvs: Vec<[f32; 2]> = reader
+                .read_tex_coords(0)
+                .map(|uvs| uvs.into_f32().collect())
+                .unwrap_or_default();
+            let colors: Vec<[u8; 4]> = reader
+                .read_colors(0)
+                .map(|colors| colors.into_rgba_u8().collect())
+                .unwrap_or_default();
+            let normals: Vec<[f32; 3]> = reader
+                .read_normals()
+                .map(|normals| normals.collect())
+                .unwrap_or_default();
+            let indices: Vec<u32> = match reader.read_indices() {
+                Some(indices) => indices.into_u32().collect(),
+                None => (0..positions.len() as u32).collect(),
+            };
+
+            let texture = match primitive
+                .material()
+                .pbr_metallic_roughness()
+                .base_color_texture()
+            {
+                Some(info) => {
+                    let image = info.texture().source().index();
+                    match image_textures.get(&image) {
+                        Some(&t) => Some(t),
+                        None => {
+                            let data = &images[image];
+                            model.textures.push(ImportedTexture {
+                                name: format!("image{}", image),
+                                width: data.width as usize,
+                                height: data.height as usize,
+                                rgba: gltf_image_to_rgba(data)?,
+                            });
+                            image_textures.insert(image, model.textures.len() - 1);
+                            Some(model.textures.len() - 1)
+                        }
+                    }
+                }
+                None => None,
+            };
+
+            for tri in indices.chunks_exact(3) {
+                let base = model.mesh.vertices.len() as u32;
+                for &i in tri {
+                    let i = i as usize;
+                    model.mesh.vertices.push(MeshVertex {
+                        pos: *positions.get(i).context("glTF index out of range")?,
+                        uv: uvs.get(i).copied().unwrap_or_default(),
+                        color: colors.get(i).copied().unwrap_or([0xFF; 4]),
+                        normal: normals.get(i).copied(),
+                    });
+                }
+                model.mesh.triangles.push(MeshTriangle {
+                    indices: [base, base + 1, base + 2],
+                    texture,
+                });
+            }
+        }
+    }
+
+    Ok(model)
+}
+
+fn gltf_image_to_rgba(data: &gltf::image::Data) -> Result<Vec<u8>> {
+    use gltf::image::Format;
+
+    Ok(match data.format {
+        Format::R8G8B8A8 => data.pixels.clone(),
+        Format::R8G8B8 => data
+            .pixels
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 0xFF])
+            .collect(),
+        Format::R8G8 => data
+            .pixels
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        Format::R8 => data.pixels.iter().flat_map(|&p| [p, p, p, 0xFF]).collect(),
+        format => bail!("Unsupported glTF image format: {:?}", format),
+    })
+}
+
+const IDENTITY: [[f32; 4]; 4] = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+/// Multiplies two column-major 4x4 matrices.
+fn mat_mul(a: &[[f32; 4]; 4], b: &[[f32; 4]; 4]) -> [[f32; 4]; 4] {
+    std::array::from_fn(|col| {
+        std::array::from_fn(|row| (0..4).map(|k| a[k][row] * b[col][k]).sum())
+    })
+}
+
+fn transform_point(m: &[[f32; 4]; 4], p: [f32; 3]) -> [f32; 3] {
+    std::array::from_fn(|row| m[0][row] * p[0] + m[1][row] * p[1] + m[2][row] * p[2] + m[3][row])
+}
+
+/// Settings for turning an imported model into a display list.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuildOptions {
+    /// Prefix for the symbols in the generated C source.
+    pub name: String,
+    /// The segment the binary blob is expected to be loaded into.
+    pub segment: u8,
+    /// Factor applied to positions before rounding to `s16`.
+    pub scale: f32,
+    /// Store normals instead of colors and enable `G_LIGHTING`.
+    pub lighting: bool,
+}
+
+impl Default for BuildOptions {
+    fn default() -> Self {
+        Self {
+            name: "model".to_owned(),
+            segment: 6,
+            scale: 1.0,
+            lighting: false,
+        }
+    }
+}
+
+/// A display list generated from an imported model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneratedDisplayList {
+    /// Textures, vertices and the display list, in that order.
+    pub binary: Vec<u8>,
+    /// The same data as C source using GBI macros.
+    pub c_source: String,
+    /// Offset of the display list inside `binary`.
+    pub dl_offset: usize,
+}
+
+impl GeneratedDisplayList {
+    /// The segmented address of the display list, given the segment used to
+    /// build it.
+    pub fn dl_address(&self, segment: u8) -> u32 {
+        ((segment as u32) << 24) | self.dl_offset as u32
+    }
+}
+
+/// One generated command, both encoded and as a GBI macro.
+struct Gfx {
+    w0: u32,
+    w1: u32,
+    c: String,
+}
+
+fn gfx(w0: u32, w1: u32, c: impl Into<String>) -> Gfx {
+    Gfx {
+        w0,
+        w1,
+        c: c.into(),
+    }
+}
+
+/// Returns log2 of a power of two, used for tile masks.
+fn mask_bits(size: usize) -> u32 {
+    if size.is_power_of_two() {
+        size.trailing_zeros()
+    } else {
+        0
+    }
+}
+
+/// Converts a model into an F3DEX2 display list.
+///
+/// Triangles are grouped by texture and split into batches that fit the
+/// vertex cache. Textures are converted to RGBA16 and loaded with
+/// `gsDPLoadBlock`, so each must be at most [`MAX_TEXELS`] texels once its
+/// rows are padded to a whole TMEM word. The output must fit in one
+/// segment.
+pub fn build_display_list(
+    model: &ImportedModel,
+    options: &BuildOptions,
+) -> Result<GeneratedDisplayList> {
+    let name = &options.name;
+    let seg_base = (options.segment as u32) << 24;
+    let mut binary: Vec<u8> = vec![];
+    let mut c = String::from("#include <ultra64.h>\n\n");
+
+    // Textures
+    let mut texture_addrs = vec![];
+    for (i, texture) in model.textures.iter().enumerate() {
+        // LoadBlock fills TMEM a 64-bit word at a time, so each row has to
+        // be padded to 4 texels
+        let line_width = texture.width.next_multiple_of(4);
+        if line_width * texture.height > MAX_TEXELS {
+            bail!(
+                "Texture {} is {}x{}, which does not fit in TMEM as RGBA16",
+                texture.name,
+                texture.width,
+                texture.height
+            );
+        }
+
+        texture_addrs.push(seg_base | binary.len() as u32);
+        let texels: Vec<u16> = texture
+            .rgba
+            .chunks_exact(texture.width * 4)
+            .flat_map(|row| {
+                let row = row
+                    .chunks_exact(4)
+                    .map(|p| Color::RGBA(p[0], p[1], p[2], p[3]).to_u16());
+                row.chain(std::iter::repeat(0)).take(line_width)
+            })
+            .collect();
+        writeln!(c, "u16 {}_tex_{}[] = {{", name, i)?;
+        for row in texels.chunks(8) {
+            let row: Vec<String> = row.iter().map(|t| format!("0x{:04X}", t)).collect();
+            writeln!(c, "    {},", row.join(", "))?;
+        }
+        writeln!(c, "}};\n")?;
+        binary.extend(texels.iter().flat_map(|t| t.to_be_bytes()));
+        while binary.len() % 8 != 0 {
+            binary.push(0);
+        }
+    }
+
+    // Vertices and commands, batched per texture
+    let mut commands: Vec<Gfx> = vec![];
+    let mut batches = 0;
+    if options.lighting {
+        commands.push(gfx(
+            0xD9FF_FFFF,
+            0x0020_0000,
+            "gsSPSetGeometryMode(G_LIGHTING)",
+        ));
+    } else {
+        commands.push(gfx(0xD9DF_FFFF, 0, "gsSPClearGeometryMode(G_LIGHTING)"));
+    }
+
+    for (texture, tris) in group_by_texture(&model.mesh.triangles) {
+        let size = texture.map(|t| (model.textures[t].width, model.textures[t].height));
+        commands.push(gfx(0xE700_0000, 0, "gsDPPipeSync()"));
+        match (texture, size) {
+            (Some(t), Some((width, height))) => {
+                texture_commands(&mut commands, name, t, texture_addrs[t], width, height);
+            }
+            _ => {
+                commands.push(gfx(
+                    0xFCFF_FFFF,
+                    0xFFFE_793C,
+                    "gsDPSetCombineMode(G_CC_SHADE, G_CC_SHADE)",
+                ));
+                commands.push(gfx(
+                    0xD700_0000,
+                    0xFFFF_FFFF,
+                    "gsSPTexture(0xFFFF, 0xFFFF, 0, G_TX_RENDERTILE, G_OFF)",
+                ));
+            }
+        }
+
+        for batch in vertex_batches(&model.mesh, &tris) {
+            let vtx_addr = seg_base | binary.len() as u32;
+            writeln!(c, "Vtx {}_vtx_{}[] = {{", name, batches)?;
+            for &v in &batch.vertices {
+                let vtx = to_vtx(&model.mesh.vertices[v as usize], size, options);
+                binary.extend(vtx_bytes(&vtx));
+                writeln!(
+                    c,
+                    "    {{{{{{{}, {}, {}}}, 0, {{{}, {}}}, {{0x{:02X}, 0x{:02X}, 0x{:02X}, 0x{:02X}}}}}}},",
+                    vtx.pos[0], vtx.pos[1], vtx.pos[2], vtx.tc[0], vtx.tc[1],
+                    vtx.cn[0], vtx.cn[1], vtx.cn[2], vtx.cn[3]
+                )?;
+            }
+            writeln!(c, "}};\n")?;
+
+            let n = batch.vertices.len() as u32;
+            commands.push(gfx(
+                0x0100_0000 | (n << 12) | (n << 1),
+                vtx_addr,
+                format!("gsSPVertex({}_vtx_{}, {}, 0)", name, batches, n),
+            ));
+            for pair in batch.triangles.chunks(2) {
+                let [a, b, c0] = pair[0];
+                let w = |t: [u32; 3]| ((t[0] * 2) << 16) | ((t[1] * 2) << 8) | (t[2] * 2);
+                match pair.get(1) {
+                    Some(&second) => {
+                        let [d, e, f] = second;
+                        commands.push(gfx(
+                            0x0600_0000 | w(pair[0]),
+                            w(second),
+                            format!(
+                                "gsSP2Triangles({}, {}, {}, 0, {}, {}, {}, 0)",
+                                a, b, c0, d, e, f
+                            ),
+                        ));
+                    }
+                    None => commands.push(gfx(
+                        0x0500_0000 | w(pair[0]),
+                        0,
+                        format!("gsSP1Triangle({}, {}, {}, 0)", a, b, c0),
+                    )),
+                }
+            }
+            batches += 1;
+        }
+    }
+    commands.push(gfx(0xDF00_0000, 0, "gsSPEndDisplayList()"));
+
+    // Display list
+    let dl_offset = binary.len();
+    writeln!(c, "Gfx {}_dl[] = {{", name)?;
+    for command in &commands {
+        binary.extend(command.w0.to_be_bytes());
+        binary.extend(command.w1.to_be_bytes());
+        writeln!(c, "    {},", command.c)?;
+    }
+    writeln!(c, "}};")?;
+
+    if binary.len() > SEGMENT_SIZE {
+        bail!(
+            "The model takes 0x{:X} bytes, more than the 0x{:X} a segment can address",
+            binary.len(),
+            SEGMENT_SIZE
+        );
+    }
+
+    Ok(GeneratedDisplayList {
+        binary,
+        c_source: c,
+        dl_offset,
+    })
+}
+
+/// Emits the commands that load an RGBA16 texture and set up the render
+/// tile to sample it with wrapping.
+fn texture_commands(
+    commands: &mut Vec<Gfx>,
+    name: &str,
+    index: usize,
+    addr: u32,
+    width: usize,
+    height: usize,
+) {
+    // Rows are stored padded to a whole TMEM word
+    let line_width = width.next_multiple_of(4);
+    let texels = (line_width * height) as u32;
+    let words_per_row = (line_width * 2 / 8) as u32;
+    let dxt = (2048 + words_per_row - 1) / words_per_row.max(1);
+    let masks = mask_bits(width);
+    let maskt = mask_bits(height);
+    let wrap_w1 = (maskt << 14) | (masks << 4);
+    let lrs = ((width as u32 - 1) << 2) << 12;
+    let lrt = (height as u32 - 1) << 2;
+
+    commands.push(gfx(
+        0xFC12_1824,
+        0xFF33_FFFF,
+        "gsDPSetCombineMode(G_CC_MODULATERGBA, G_CC_MODULATERGBA)",
+    ));
+    commands.push(gfx(
+        0xD700_0002,
+        0xFFFF_FFFF,
+        "gsSPTexture(0xFFFF, 0xFFFF, 0, G_TX_RENDERTILE, G_ON)",
+    ));
+    commands.push(gfx(
+        0xFD10_0000,
+        addr,
+        format!(
+            "gsDPSetTextureImage(G_IM_FMT_RGBA, G_IM_SIZ_16b, 1, {}_tex_{})",
+            name, index
+        ),
+    ));
+    commands.push(gfx(
+        0xF510_0000,
+        0x0700_0000 | wrap_w1,
+        format!(
+            "gsDPSetTile(G_IM_FMT_RGBA, G_IM_SIZ_16b, 0, 0, G_TX_LOADTILE, 0, G_TX_WRAP, {}, G_TX_NOLOD, G_TX_WRAP, {}, G_TX_NOLOD)",
+            maskt, masks
+        ),
+    ));
+    commands.push(gfx(0xE600_0000, 0, "gsDPLoadSync()"));
+    commands.push(gfx(
+        0xF300_0000,
+        0x0700_0000 | ((texels - 1) << 12) | dxt,
+        format!(
+            "gsDPLoadBlock(G_TX_LOADTILE, 0, 0, {}, {})",
+            texels - 1,
+            dxt
+        ),
+    ));
+    commands.push(gfx(0xE700_0000, 0, "gsDPPipeSync()"));
+    commands.push(gfx(
+        0xF510_0000 | (words_per_row << 9),
+        wrap_w1,
+        format!(
+            "gsDPSetTile(G_IM_FMT_RGBA, G_IM_SIZ_16b, {}, 0, G_TX_RENDERTILE, 0, G_TX_WRAP, {}, G_TX_NOLOD, G_TX_WRAP, {}, G_TX_NOLOD)",
+            words_per_row, maskt, masks
+        ),
+    ));
+    commands.push(gfx(
+        0xF200_0000,
+        lrs | lrt,
+        format!(
+            "gsDPSetTileSize(G_TX_RENDERTILE, 0, 0, {} << G_TEXTURE_IMAGE_FRAC, {} << G_TEXTURE_IMAGE_FRAC)",
+            width - 1,
+            height - 1
+        ),
+    ));
+}
+
+/// A run of triangles whose vertices fit in the vertex cache together.
+struct VertexBatch {
+    /// Mesh vertex indices to load, in cache order.
+    vertices: Vec<u32>,
+    /// Triangles as cache indices.
+    triangles: Vec<[u32; 3]>,
+}
+
+/// Greedily packs triangles into vertex-cache-sized batches.
+fn vertex_batches(mesh: &Mesh, tris: &[&MeshTriangle]) -> Vec<VertexBatch> {
+    let mut batches: Vec<VertexBatch> = vec![];
+    let mut current = VertexBatch {
+        vertices: vec![],
+        triangles: vec![],
+    };
+    let mut cache: HashMap<[u32; 9], u32> = HashMap::new();
+
+    // Identical vertices are shared within a batch.
+    let key = |v: &MeshVertex| -> [u32; 9] {
+        [
+            v.pos[0].to_bits(),
+            v.pos[1].to_bits(),
+            v.pos[2].to_bits(),
+            v.uv[0].to_bits(),
+            v.uv[1].to_bits(),
+            u32::from_be_bytes(v.color),
+            v.normal.map(|n| n[0].to_bits()).unwrap_or(0),
+            v.normal.map(|n| n[1].to_bits()).unwrap_or(0),
+            v.normal.map(|n| n[2].to_bits()).unwrap_or(0),
+        ]
+    };
+
+    for tri in tris {
+        let new_vertices = tri
+            .indices
+            .iter()
+            .filter(|&&i| !cache.contains_key(&key(&mesh.vertices[i as usize])))
+            .count();
+        if current.vertices.len() + new_vertices > VTX_CACHE_SIZE {
+            batches.push(std::mem::replace(
+                &mut current,
+                VertexBatch {
+                    vertices: vec![],
+                    triangles: vec![],
+                },
+            ));
+            cache.clear();
+        }
+
+        let cached = tri.indices.map(|i| {
+            *cache
+                .entry(key(&mesh.vertices[i as usize]))
+                .or_insert_with(|| {
+                    current.vertices.push(i);
+                    current.vertices.len() as u32 - 1
+                })
+        });
+        current.triangles.push(cached);
+    }
+    if !current.triangles.is_empty() {
+        batches.push(current);
+    }
+    batches
+}
+
+/// Converts a mesh vertex to a `Vtx`, scaling UVs to texels in 10.5 fixed
+/// point.
+fn to_vtx(v: &MeshVertex, texture_size: Option<(usize, usize)>, options: &BuildOptions) -> Vtx {
+    let to_s16 = |f: f32| f.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+    let (width, height) = texture_size.unwrap_or((0, 0));
+
+    Vtx {
+        pos: v.pos.map(|p| to_s16(p * options.scale)),
+        flag: 0,
+        tc: [
+            to_s16(v.uv[0] * width as f32 * 32.0),
+            to_s16(v.uv[1] * height as f32 * 32.0),
+        ],
+        cn: match (options.lighting, v.normal) {
+            (true, Some(n)) => {
+                let len = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2])
+                    .sqrt()
+                    .max(f32::EPSILON);
+                let [x, y, z] = n.map(|c| (c / len * 127.0).round() as i8 as u8);
+                [x, y, z, v.color[3]]
+            }
+            // Facing the viewer, rather than reading the color as a normal
+            (true, None) => [0, 0, 127, v.color[3]],
+            (false, _) => v.color,
+        },
+    }
+}
+
+fn vtx_bytes(vtx: &Vtx) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(Vtx::SIZE);
+    for p in vtx.pos {
+        bytes.extend(p.to_be_bytes());
+    }
+    bytes.extend(vtx.flag.to_be_bytes());
+    for t in vtx.tc {
+        bytes.extend(t.to_be_bytes());
+    }
+    bytes.extend(vtx.cn);
+    bytes
+}
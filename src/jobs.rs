@@ -0,0 +1,211 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+};
+
+use anyhow::{bail, Result};
+use eframe::egui;
+
+/// The progress and cancellation flag shared between a job and the UI.
+#[derive(Debug, Default)]
+struct JobState {
+    /// The fraction done, as `f32` bits.
+    progress: AtomicU32,
+    cancelled: AtomicBool,
+}
+
+/// Handed to a running job to report progress and check for cancellation.
+#[derive(Debug, Clone)]
+pub struct JobContext {
+    state: Arc<JobState>,
+    ctx: Option<egui::Context>,
+}
+
+impl JobContext {
+    /// Sets the fraction of the job done, from 0 to 1, and repaints the UI
+    /// so the progress bar moves.
+    pub fn set_progress(&self, fraction: f32) {
+        self.state
+            .progress
+            .store(fraction.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
+        if let Some(ctx) = &self.ctx {
+            ctx.request_repaint();
+        }
+    }
+
+    /// Whether the job was cancelled. Jobs should check this regularly and
+    /// return early once it is set.
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Sets the progress and returns whether the job should go on, which
+    /// fits the progress callbacks of the search functions.
+    pub fn tick(&self, fraction: f32) -> bool {
+        self.set_progress(fraction);
+        !self.is_cancelled()
+    }
+
+    /// Fails if the job was cancelled, for use with `?`.
+    pub fn check_cancelled(&self) -> Result<()> {
+        if self.is_cancelled() {
+            bail!("Cancelled");
+        }
+        Ok(())
+    }
+}
+
+/// A job running on a worker thread.
+struct Job<T> {
+    id: u64,
+    name: String,
+    state: Arc<JobState>,
+    receiver: mpsc::Receiver<Result<T>>,
+}
+
+impl<T> Job<T> {
+    fn progress(&self) -> f32 {
+        f32::from_bits(self.state.progress.load(Ordering::Relaxed))
+    }
+}
+
+/// A job that has finished, with what it returned.
+#[derive(Debug)]
+pub struct FinishedJob<T> {
+    pub id: u64,
+    pub name: String,
+    pub result: Result<T>,
+}
+
+/// Runs long tasks on worker threads so the UI stays responsive.
+///
+/// Jobs return a `T`, usually an enum with a variant per kind of job, which
+/// `poll` hands back on the UI thread. Results of cancelled jobs are
+/// dropped.
+pub struct Jobs<T> {
+    next_id: u64,
+    running: Vec<Job<T>>,
+}
+
+impl<T> Default for Jobs<T> {
+    fn default() -> Self {
+        Self {
+            next_id: 1,
+            running: vec![],
+        }
+    }
+}
+
+impl<T: Send + 'static> Jobs<T> {
+    /// Starts a job on a new worker thread.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context to repaint when the job makes progress or
+    ///   finishes; `None` outside the UI.
+    /// * `name` - Shown in the jobs panel.
+    /// * `work` - The task; it should check `JobContext::is_cancelled`
+    ///   regularly.
+    ///
+    /// # Returns
+    ///
+    /// The id of the job, which its result is returned with.
+    pub fn spawn<F>(&mut self, ctx: Option<&egui::Context>, name: &str, work: F) -> u64
+    where
+        F: FnOnce(&JobContext) -> Result<T> + Send + 'static,
+    {
+        let id = self.next_id;
+        self.next_id += 1;
+        let state = Arc::new(JobState::default());
+        let job_ctx = JobContext {
+            state: state.clone(),
+            ctx: ctx.cloned(),
+        };
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let result = work(&job_ctx);
+            // The receiver is gone if the jobs were dropped; nobody is
+            // waiting for the result then
+            let _ = sender.send(result);
+            if let Some(ctx) = &job_ctx.ctx {
+                ctx.request_repaint();
+            }
+        });
+        self.running.push(Job {
+            id,
+            name: name.to_owned(),
+            state,
+            receiver,
+        });
+        id
+    }
+
+    /// Collects the jobs that have finished since the last call.
+    pub fn poll(&mut self) -> Vec<FinishedJob<T>> {
+        let mut finished = vec![];
+        self.running.retain(|job| {
+            let result = match job.receiver.try_recv() {
+                Ok(result) => result,
+                Err(mpsc::TryRecvError::Empty) => return true,
+                Err(mpsc::TryRecvError::Disconnected) => Err(anyhow::anyhow!("Job panicked")),
+            };
+            if !job.state.cancelled.load(Ordering::Relaxed) {
+                finished.push(FinishedJob {
+                    id: job.id,
+                    name: job.name.clone(),
+                    result,
+                });
+            }
+            false
+        });
+        finished
+    }
+
+    /// Asks a job to stop. It is removed once its thread returns.
+    pub fn cancel(&mut self, id: u64) {
+        if let Some(job) = self.running.iter().find(|job| job.id == id) {
+            job.state.cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Whether a job with `id` is still running.
+    pub fn is_running(&self, id: u64) -> bool {
+        self.running.iter().any(|job| job.id == id)
+    }
+
+    /// Whether no jobs are running.
+    pub fn is_empty(&self) -> bool {
+        self.running.is_empty()
+    }
+
+    /// Draws a progress bar and a Cancel button for each running job.
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        let mut cancel = None;
+        for job in &self.running {
+            ui.horizontal(|ui| {
+                let cancelled = job.state.cancelled.load(Ordering::Relaxed);
+                ui.add_enabled_ui(!cancelled, |ui| {
+                    if ui.button("Cancel").clicked() {
+                        cancel = Some(job.id);
+                    }
+                });
+                let text = if cancelled {
+                    format!("{} (cancelling)", job.name)
+                } else {
+                    job.name.clone()
+                };
+                ui.add(
+                    egui::ProgressBar::new(job.progress())
+                        .text(text)
+                        .show_percentage(),
+                );
+            });
+        }
+        if let Some(id) = cancel {
+            self.cancel(id);
+        }
+    }
+}
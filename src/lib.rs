@@ -1,4 +1,21 @@
 pub mod app;
 pub mod bin_handler;
+pub mod combiner;
+pub mod diff;
+pub mod display_list;
+pub mod duplicates;
+pub mod export;
+pub mod hex_view;
+pub mod import;
+pub mod jobs;
+pub mod minimap;
+pub mod model_view;
 pub mod motex_options;
+pub mod onion;
+pub mod savestate;
+pub mod search;
+pub mod segments;
+pub mod texture_search;
 pub mod texview;
+pub mod vtx_view;
+pub mod watcher;
@@ -0,0 +1,318 @@
+use std::borrow::Cow;
+
+use eframe::egui::{self, Color32, ColorImage, Sense, TextureHandle, TextureOptions};
+
+/// The most blocks the file is split into, which bounds the work done when
+/// a large ROM is opened.
+const MAX_BLOCKS: usize = 4096;
+
+/// The smallest block that is classified, in bytes.
+const MIN_BLOCK_SIZE: usize = 256;
+
+/// The most bytes of a block that are read to classify it. Larger blocks
+/// are sampled in `SAMPLE_WINDOWS` evenly spread windows, so the map of a
+/// multi-gigabyte file is built from a few megabytes.
+pub const SAMPLE_SIZE: usize = 4096;
+
+/// The number of windows a large block is sampled in.
+const SAMPLE_WINDOWS: usize = 4;
+
+/// How many blocks are classified between progress reports.
+const PROGRESS_INTERVAL: usize = 64;
+
+/// Entropy above which a block is taken to be compressed, in bits per byte.
+const HIGH_ENTROPY: f32 = 7.2;
+
+/// The share of words that must look like MIPS instructions for a block to
+/// be taken as code.
+const CODE_RATIO: f32 = 0.7;
+
+/// The SPECIAL function codes compilers emit most.
+const SPECIAL_FUNCTS: [u32; 19] = [
+    0x00, 0x02, 0x03, 0x08, 0x09, 0x10, 0x12, 0x18, 0x19, 0x1A, 0x1B, 0x21, 0x23, 0x24, 0x25, 0x26,
+    0x27, 0x2A, 0x2B,
+];
+
+/// What a block of the file most likely holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteClass {
+    /// Only zero bytes, usually padding.
+    Zero,
+    /// Repetitive data such as uncompressed textures, vertices or tables.
+    LowEntropy,
+    /// Close to random data, usually compressed or encrypted.
+    HighEntropy,
+    /// Words that decode as common MIPS instructions.
+    Code,
+}
+
+impl ByteClass {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ByteClass::Zero => "Zero fill",
+            ByteClass::LowEntropy => "Data",
+            ByteClass::HighEntropy => "Compressed",
+            ByteClass::Code => "Code",
+        }
+    }
+
+    pub fn color(&self) -> Color32 {
+        match self {
+            ByteClass::Zero => Color32::from_gray(24),
+            ByteClass::LowEntropy => Color32::from_rgb(60, 160, 80),
+            ByteClass::HighEntropy => Color32::from_rgb(200, 70, 60),
+            ByteClass::Code => Color32::from_rgb(70, 110, 210),
+        }
+    }
+}
+
+/// Returns the Shannon entropy of `bytes`, in bits per byte.
+pub fn entropy(bytes: &[u8]) -> f32 {
+    if bytes.is_empty() {
+        return 0.0;
+    }
+    let mut counts = [0usize; 256];
+    for &b in bytes {
+        counts[b as usize] += 1;
+    }
+    let len = bytes.len() as f32;
+    counts
+        .iter()
+        .filter(|&&c| c != 0)
+        .map(|&c| {
+            let p = c as f32 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Whether a big-endian word looks like one of the MIPS instructions that
+/// make up most of compiled N64 code.
+fn is_common_instruction(word: u32) -> bool {
+    if word == 0 {
+        // nop, but just as likely padding
+        return false;
+    }
+    match word >> 26 {
+        // SPECIAL: shifts, jumps, moves, multiplies and ALU operations
+        0x00 => SPECIAL_FUNCTS.contains(&(word & 0x3F)),
+        // REGIMM, j, jal, beq, bne, blez, bgtz, addiu, slti, sltiu, andi,
+        // ori, xori, lui, COP0, COP1, beql, bnel
+        0x01..=0x07 | 0x09..=0x0F | 0x10 | 0x11 | 0x14 | 0x15 => true,
+        // lb, lh, lw, lbu, lhu, sb, sh, sw, lwc1, ldc1, swc1, sdc1, ld, sd
+        0x20 | 0x21 | 0x23 | 0x24 | 0x25 | 0x28 | 0x29 | 0x2B | 0x31 | 0x35 | 0x37 | 0x39
+        | 0x3D | 0x3F => true,
+        _ => false,
+    }
+}
+
+/// Classifies one block of the file.
+pub fn classify_block(bytes: &[u8]) -> ByteClass {
+    if bytes.iter().all(|&b| b == 0) {
+        return ByteClass::Zero;
+    }
+
+    let words: Vec<u32> = bytes
+        .chunks_exact(4)
+        .map(|w| u32::from_be_bytes([w[0], w[1], w[2], w[3]]))
+        .collect();
+    let nonzero = words.iter().filter(|&&w| w != 0).count();
+    let code = words.iter().filter(|&&w| is_common_instruction(w)).count();
+    // Fill patterns also decode as instructions, but code rarely repeats
+    let mut distinct = words.clone();
+    distinct.sort_unstable();
+    distinct.dedup();
+    if nonzero > 0 && code as f32 >= nonzero as f32 * CODE_RATIO && distinct.len() * 2 >= nonzero {
+        return ByteClass::Code;
+    }
+
+    if entropy(bytes) > HIGH_ENTROPY {
+        ByteClass::HighEntropy
+    } else {
+        ByteClass::LowEntropy
+    }
+}
+
+/// Returns the block size used to split a file of `len` bytes.
+pub fn block_size(len: usize) -> usize {
+    len.div_ceil(MAX_BLOCKS)
+        .next_multiple_of(4)
+        .max(MIN_BLOCK_SIZE)
+}
+
+/// Returns the bytes of `block` that are classified: all of it when it is
+/// at most `SAMPLE_SIZE` bytes, otherwise evenly spread windows of it.
+pub fn sample_block(block: &[u8]) -> Cow<'_, [u8]> {
+    if block.len() <= SAMPLE_SIZE {
+        return Cow::Borrowed(block);
+    }
+    // Keep windows word-aligned so code is still recognized
+    let window = SAMPLE_SIZE / SAMPLE_WINDOWS;
+    let step = ((block.len() - window) / (SAMPLE_WINDOWS - 1)) & !3;
+    Cow::Owned(
+        (0..SAMPLE_WINDOWS)
+            .flat_map(|i| &block[i * step..i * step + window])
+            .copied()
+            .collect(),
+    )
+}
+
+/// Classifies `data` in blocks of `block_size` bytes, sampling blocks
+/// larger than `SAMPLE_SIZE`.
+pub fn classify(data: &[u8], block_size: usize) -> Vec<ByteClass> {
+    classify_with_progress(data, block_size, |_| true)
+}
+
+/// Like `classify`, but reports the fraction of blocks classified to
+/// `progress` every `PROGRESS_INTERVAL` blocks. Classifying stops early,
+/// keeping the classes so far, when `progress` returns `false`.
+pub fn classify_with_progress(
+    data: &[u8],
+    block_size: usize,
+    mut progress: impl FnMut(f32) -> bool,
+) -> Vec<ByteClass> {
+    let blocks = data.len().div_ceil(block_size);
+    let mut classes = Vec::with_capacity(blocks);
+    for (i, block) in data.chunks(block_size).enumerate() {
+        if i % PROGRESS_INTERVAL == 0 && !progress(i as f32 / blocks as f32) {
+            break;
+        }
+        classes.push(classify_block(&sample_block(block)));
+    }
+    classes
+}
+
+/// A vertical strip showing the byte class of the whole file.
+///
+/// Classifying a large file takes a while, so the owner runs
+/// `classify_with_progress` in the background whenever `request` asks for
+/// it and hands the classes back with `set_classes`.
+#[derive(Default)]
+pub struct Minimap {
+    classes: Vec<ByteClass>,
+    block_size: usize,
+    tex: Option<TextureHandle>,
+    /// The `BinFile::generation` the map was built from.
+    generation: u64,
+    /// The `BinFile::generation` classes were last asked for.
+    requested: u64,
+}
+
+impl Minimap {
+    /// Notes the generation of the file about to be drawn.
+    ///
+    /// Returns whether it changed since the last call, in which case the
+    /// file needs classifying again.
+    pub fn request(&mut self, generation: u64) -> bool {
+        if self.requested == generation {
+            return false;
+        }
+        self.requested = generation;
+        true
+    }
+
+    /// Stores the classes of a file, unless a newer generation was
+    /// requested since they were asked for.
+    ///
+    /// # Arguments
+    /// * `generation` - The `BinFile::generation` that was classified.
+    /// * `block_size` - The block size the file was split in.
+    /// * `classes` - The class of each block.
+    pub fn set_classes(&mut self, generation: u64, block_size: usize, classes: Vec<ByteClass>) {
+        if generation != self.requested {
+            return;
+        }
+        self.generation = generation;
+        self.block_size = block_size;
+        self.classes = classes;
+        self.tex = None;
+    }
+
+    /// Uploads the map once new classes came in.
+    fn update(&mut self, ctx: &egui::Context) {
+        if self.tex.is_some() {
+            return;
+        }
+
+        let pixels = self.classes.iter().map(ByteClass::color).collect();
+        let image = ColorImage {
+            size: [1, self.classes.len().max(1)],
+            pixels: if self.classes.is_empty() {
+                vec![Color32::TRANSPARENT]
+            } else {
+                pixels
+            },
+        };
+        self.tex = Some(ctx.load_texture("minimap", image, TextureOptions::NEAREST));
+    }
+
+    /// Draws the map with a marker at `file_pos`. The strip stays empty
+    /// until the classes of `generation` are set.
+    ///
+    /// Returns the offset of the block that was clicked, if any.
+    ///
+    /// # Arguments
+    /// * `ui` - The egui ui to draw into.
+    /// * `ctx` - The egui context, used to upload the map.
+    /// * `data` - The whole file.
+    /// * `generation` - The `BinFile::generation` of `data`.
+    /// * `file_pos` - The current position in the file.
+    /// * `size` - The size of the strip.
+    pub fn draw(
+        &mut self,
+        ui: &mut egui::Ui,
+        ctx: &egui::Context,
+        data: &[u8],
+        generation: u64,
+        file_pos: usize,
+        size: egui::Vec2,
+    ) -> Option<usize> {
+        let (rect, response) = ui.allocate_exact_size(size, Sense::click_and_drag());
+        if data.is_empty() || self.generation != generation {
+            return None;
+        }
+        self.update(ctx);
+
+        let painter = ui.painter_at(rect);
+        if let Some(tex) = &self.tex {
+            painter.image(
+                tex.id(),
+                rect,
+                egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+                Color32::WHITE,
+            );
+        }
+
+        let len = data.len() as f32;
+        let marker_y = rect.top() + file_pos as f32 / len * rect.height();
+        painter.hline(rect.x_range(), marker_y, (2.0, Color32::YELLOW));
+
+        let offset_at = |y: f32| {
+            let fraction = ((y - rect.top()) / rect.height()).clamp(0.0, 1.0);
+            ((fraction * len) as usize).min(data.len() - 1)
+        };
+
+        let response = match response.hover_pos() {
+            Some(pos) => {
+                let offset = offset_at(pos.y);
+                match self.classes.get(offset / self.block_size) {
+                    Some(class) => response.on_hover_text_at_pointer(format!(
+                        "0x{:08X}: {}",
+                        offset,
+                        class.name()
+                    )),
+                    None => response,
+                }
+            }
+            None => response,
+        };
+
+        if response.clicked() || response.dragged() {
+            let pos = response.interact_pointer_pos()?;
+            let offset = offset_at(pos.y);
+            // Land on the start of the block
+            return Some(offset - offset % self.block_size);
+        }
+        None
+    }
+}
@@ -0,0 +1,338 @@
+use anyhow::Result;
+use eframe::egui::{
+    self, epaint::Vertex, Color32, ColorImage, Pos2, Sense, Shape, TextureHandle, TextureId,
+    TextureOptions, TextureWrapMode,
+};
+
+use crate::{
+    display_list::{Interpreter, Mesh, Microcode, TextureDescriptor, TileDescriptor},
+    segments::{address_link, AddressSpace, SegmentTable},
+};
+
+/// Vertical field of view of the camera, in radians.
+const FOV_Y: f32 = std::f32::consts::FRAC_PI_3;
+
+/// Renders the mesh produced by a display list with an orbit camera.
+///
+/// Triangles are projected on the CPU and drawn back to front through the
+/// egui painter, so no GPU features beyond plain textured meshes are needed.
+pub struct ModelView {
+    /// The segmented address of the display list to run.
+    pub dl_addr: u32,
+    /// The microcode used to decode the display list.
+    pub ucode: Microcode,
+    /// Camera rotation around the vertical axis, in radians.
+    pub yaw: f32,
+    /// Camera rotation around the horizontal axis, in radians.
+    pub pitch: f32,
+    /// Distance of the camera from the center of the mesh.
+    pub distance: f32,
+    mesh: Option<Mesh>,
+    textures: Vec<TextureHandle>,
+    center: [f32; 3],
+    radius: f32,
+    /// Where the viewport was drawn last frame.
+    viewport: egui::Rect,
+}
+
+impl Default for ModelView {
+    fn default() -> Self {
+        Self {
+            dl_addr: 0,
+            ucode: Microcode::default(),
+            yaw: 0.0,
+            pitch: 0.0,
+            distance: 1.0,
+            mesh: None,
+            textures: vec![],
+            center: [0.0; 3],
+            radius: 1.0,
+            viewport: egui::Rect::NOTHING,
+        }
+    }
+}
+
+impl ModelView {
+    /// The mesh from the last successful load.
+    pub fn mesh(&self) -> Option<&Mesh> {
+        self.mesh.as_ref()
+    }
+
+    /// Whether `pointer` is over the viewport as it was drawn last frame, so
+    /// input handled before drawing can leave its scrolling alone.
+    pub fn is_hovered(&self, pointer: Option<egui::Pos2>) -> bool {
+        pointer.is_some_and(|pos| self.viewport.contains(pos))
+    }
+
+    /// Runs the display list at `dl_addr` and uploads its textures.
+    ///
+    /// # Arguments
+    /// * `ctx` - The egui context, used to upload textures.
+    /// * `memory` - The address space the display list is read from.
+    pub fn load(&mut self, ctx: &egui::Context, memory: &AddressSpace) -> Result<()> {
+        let mesh = Interpreter::new(memory, self.ucode).run(self.dl_addr)?;
+
+        self.textures = mesh
+            .textures
+            .iter()
+            .enumerate()
+            .map(|(i, desc)| {
+                ctx.load_texture(
+                    format!("model_tex_{}", i),
+                    decode_descriptor(memory, desc),
+                    texture_options(&desc.tile),
+                )
+            })
+            .collect();
+
+        if let Some((min, max)) = mesh.bounds() {
+            self.center = std::array::from_fn(|i| (min[i] + max[i]) / 2.0);
+            self.radius = (0..3)
+                .map(|i| (max[i] - min[i]) / 2.0)
+                .map(|d| d * d)
+                .sum::<f32>()
+                .sqrt()
+                .max(1.0);
+        }
+        self.mesh = Some(mesh);
+        self.reset_camera();
+        Ok(())
+    }
+
+    /// Points the camera at the whole mesh.
+    pub fn reset_camera(&mut self) {
+        self.yaw = 0.5;
+        self.pitch = 0.3;
+        self.distance = self.radius / (FOV_Y / 2.0).tan() * 1.2;
+    }
+
+    /// Draws the viewport and handles orbit and zoom input.
+    ///
+    /// # Arguments
+    /// * `ui` - The egui ui to draw into.
+    /// * `size` - The size of the viewport.
+    pub fn draw(&mut self, ui: &mut egui::Ui, size: egui::Vec2) {
+        let (rect, response) = ui.allocate_exact_size(size, Sense::drag());
+        self.viewport = rect;
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, Color32::from_gray(32));
+
+        if response.dragged() {
+            let delta = response.drag_delta();
+            self.yaw += delta.x * 0.01;
+            self.pitch = (self.pitch + delta.y * 0.01).clamp(-1.5, 1.5);
+        }
+        if response.hovered() {
+            let scroll = ui.input(|i| i.smooth_scroll_delta.y);
+            self.distance = (self.distance * (-scroll * 0.002).exp()).max(self.radius * 0.05);
+        }
+
+        let Some(mesh) = &self.mesh else {
+            painter.text(
+                rect.center(),
+                egui::Align2::CENTER_CENTER,
+                "No display list loaded",
+                egui::FontId::default(),
+                ui.visuals().text_color(),
+            );
+            return;
+        };
+
+        let focal = rect.height() / 2.0 / (FOV_Y / 2.0).tan();
+        let near = self.radius * 0.01;
+        let light = normalize([0.4, 0.8, 0.4]);
+
+        // Project every vertex once, keeping the view depth for sorting.
+        let projected: Vec<Option<(Pos2, f32)>> = mesh
+            .vertices
+            .iter()
+            .map(|v| {
+                let [x, y, z] = self.to_view(v.pos);
+                let depth = self.distance - z;
+                (depth > near).then(|| {
+                    (
+                        rect.center() + egui::vec2(x * focal / depth, -y * focal / depth),
+                        depth,
+                    )
+                })
+            })
+            .collect();
+
+        let mut order: Vec<(usize, f32)> = mesh
+            .triangles
+            .iter()
+            .enumerate()
+            .filter_map(|(i, tri)| {
+                let depth = tri
+                    .indices
+                    .iter()
+                    .map(|&v| projected[v as usize].map(|(_, d)| d))
+                    .sum::<Option<f32>>()?;
+                Some((i, depth))
+            })
+            .collect();
+        order.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        // Batch consecutive triangles that share a texture into one mesh.
+        let mut batch = egui::Mesh::default();
+        for (i, _) in order {
+            let tri = &mesh.triangles[i];
+            let texture_id = match tri.texture {
+                Some(t) => self.textures[t].id(),
+                None => TextureId::default(),
+            };
+            if batch.texture_id != texture_id {
+                if !batch.is_empty() {
+                    painter.add(Shape::mesh(std::mem::take(&mut batch)));
+                }
+                batch = egui::Mesh::with_texture(texture_id);
+            }
+
+            for &index in &tri.indices {
+                let v = &mesh.vertices[index as usize];
+                let (pos, _) = projected[index as usize].unwrap();
+                let shade = v
+                    .normal
+                    .map(|n| 0.4 + 0.6 * dot(n, light).max(0.0))
+                    .unwrap_or(1.0);
+                let [r, g, b, a] = v.color;
+                let shade = |c: u8| (c as f32 * shade) as u8;
+
+                batch.vertices.push(Vertex {
+                    pos,
+                    uv: match tri.texture {
+                        Some(_) => egui::pos2(v.uv[0], v.uv[1]),
+                        None => egui::epaint::WHITE_UV,
+                    },
+                    color: Color32::from_rgba_unmultiplied(shade(r), shade(g), shade(b), a),
+                });
+            }
+            let base = batch.vertices.len() as u32 - 3;
+            batch.add_triangle(base, base + 1, base + 2);
+        }
+        if !batch.is_empty() {
+            painter.add(Shape::mesh(batch));
+        }
+    }
+
+    /// Lists the commands that were executed, indented by call depth.
+    ///
+    /// Returns the address of a clicked address link, if any.
+    pub fn command_list(&self, ui: &mut egui::Ui, segments: &SegmentTable) -> Option<u32> {
+        let Some(mesh) = &self.mesh else {
+            return None;
+        };
+
+        let mut clicked = None;
+        let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+        egui::ScrollArea::vertical()
+            .id_salt("dl_commands")
+            .auto_shrink([false, false])
+            .show_rows(ui, row_height, mesh.trace.len(), |ui, rows| {
+                for traced in &mesh.trace[rows] {
+                    ui.horizontal(|ui| {
+                        if address_link(ui, segments, traced.addr) {
+                            clicked = Some(traced.addr);
+                        }
+                        ui.monospace(format!("{:08X} {:08X}", traced.w0, traced.w1));
+                        ui.add_space(traced.depth as f32 * 12.0);
+                        ui.monospace(traced.command.name());
+                        if let Some(addr) = traced.command.address() {
+                            if address_link(ui, segments, addr) {
+                                clicked = Some(addr);
+                            }
+                        }
+                    });
+                }
+            });
+        clicked
+    }
+
+    /// Lists the textures the display list loaded, with a thumbnail and the
+    /// parameters needed to view each one.
+    ///
+    /// Returns the index into `Mesh::textures` of a clicked entry, if any.
+    pub fn texture_list(&self, ui: &mut egui::Ui, segments: &SegmentTable) -> Option<usize> {
+        let Some(mesh) = &self.mesh else {
+            return None;
+        };
+
+        let mut clicked = None;
+        for (i, (desc, texture)) in mesh.textures.iter().zip(&self.textures).enumerate() {
+            ui.horizontal(|ui| {
+                let thumbnail =
+                    egui::Image::new((texture.id(), egui::vec2(32.0, 32.0))).sense(Sense::click());
+                if ui.add(thumbnail).on_hover_text("View texture").clicked() {
+                    clicked = Some(i);
+                }
+                if address_link(ui, segments, desc.addr) {
+                    clicked = Some(i);
+                }
+                ui.monospace(format!("{:?} {}x{}", desc.format, desc.width, desc.height));
+                if let Some(tlut) = desc.tlut {
+                    ui.label("TLUT");
+                    if address_link(ui, segments, tlut) {
+                        clicked = Some(i);
+                    }
+                }
+            });
+        }
+        clicked
+    }
+
+    /// Transforms a model-space position into view space.
+    fn to_view(&self, pos: [f32; 3]) -> [f32; 3] {
+        orbit(
+            [
+                pos[0] - self.center[0],
+                pos[1] - self.center[1],
+                pos[2] - self.center[2],
+            ],
+            self.yaw,
+            self.pitch,
+        )
+    }
+}
+
+/// Rotates a position around the origin by an orbit camera's yaw and pitch.
+pub fn orbit(pos: [f32; 3], yaw: f32, pitch: f32) -> [f32; 3] {
+    let [x, y, z] = pos;
+    let (sin_yaw, cos_yaw) = yaw.sin_cos();
+    let (sin_pitch, cos_pitch) = pitch.sin_cos();
+
+    let x1 = x * cos_yaw - z * sin_yaw;
+    let z1 = x * sin_yaw + z * cos_yaw;
+    let y2 = y * cos_pitch - z1 * sin_pitch;
+    let z2 = y * sin_pitch + z1 * cos_pitch;
+    [x1, y2, z2]
+}
+
+/// Decodes a display list texture through the same path `TexView` uses.
+pub fn decode_descriptor(memory: &AddressSpace, desc: &TextureDescriptor) -> ColorImage {
+    ColorImage::from_rgba_unmultiplied([desc.width, desc.height], &desc.decode(memory))
+}
+
+/// Picks the closest egui sampling mode for an RDP tile.
+fn texture_options(tile: &TileDescriptor) -> TextureOptions {
+    let wrap_mode = if tile.cms & TileDescriptor::CLAMP != 0 {
+        TextureWrapMode::ClampToEdge
+    } else if tile.cms & TileDescriptor::MIRROR != 0 {
+        TextureWrapMode::MirroredRepeat
+    } else {
+        TextureWrapMode::Repeat
+    };
+
+    TextureOptions {
+        wrap_mode,
+        ..TextureOptions::NEAREST
+    }
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let len = dot(v, v).sqrt();
+    [v[0] / len, v[1] / len, v[2] / len]
+}
@@ -3,34 +3,22 @@ use eframe::egui::{self, Theme, Visuals};
 /// Represents the available theme choices for the application.
 /// Light and Dark are explicit choices, while System will
 /// default to the system theme.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub enum ThemeChoice {
     Light,
     Dark,
+    // default to System theme
+    #[default]
     System,
 }
 
-impl Default for ThemeChoice {
-    fn default() -> Self {
-        // default to System theme
-        Self::System
-    }
-}
-
 /// Manages the visual appearance settings for the application.
+#[derive(Default)]
 pub struct Appearance {
     /// The current theme choice for the application.
     pub theme_choice: ThemeChoice,
 }
 
-impl Default for Appearance {
-    fn default() -> Self {
-        Self {
-            theme_choice: ThemeChoice::default(),
-        }
-    }
-}
-
 impl Appearance {
     /// Applies the appearance settings to the egui context.
     ///
@@ -0,0 +1,167 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use eframe::egui;
+
+/// How the second image is combined with the texture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlendMode {
+    /// Drawn over the texture with partial opacity.
+    #[default]
+    Onion,
+    /// The per-channel absolute difference, black where the two match.
+    Difference,
+}
+
+/// Where the second image comes from.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum OverlaySource {
+    /// Another offset in the open file, decoded with the same settings.
+    #[default]
+    Offset,
+    /// The same offset in the compared file.
+    ComparedFile,
+    /// A PNG, e.g. the source of a re-encoded texture.
+    Png {
+        height: usize,
+        pixels: OverlayPixels,
+    },
+}
+
+/// Decoded RGBA8 pixels of a second image.
+///
+/// The pixels are shared rather than copied each frame, and every decode
+/// gets a new `id`, so two overlays are compared by `id` alone.
+#[derive(Debug, Clone)]
+pub struct OverlayPixels {
+    pub id: u64,
+    pub width: usize,
+    pub rgba: Arc<Vec<u8>>,
+}
+
+impl OverlayPixels {
+    pub fn new(width: usize, rgba: Vec<u8>) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            width,
+            rgba: Arc::new(rgba),
+        }
+    }
+}
+
+impl PartialEq for OverlayPixels {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+/// A decoded second image, ready to be blended by `TexView`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OnionImage {
+    pub pixels: OverlayPixels,
+    pub mode: BlendMode,
+    pub opacity: f32,
+}
+
+/// Settings for drawing a second image over the texture view.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OnionSkin {
+    pub enabled: bool,
+    pub source: OverlaySource,
+    /// The file offset read for `OverlaySource::Offset`.
+    pub offset: usize,
+    pub mode: BlendMode,
+    /// The opacity of the second image in `BlendMode::Onion`.
+    pub opacity: f32,
+    /// The number of pixels that differed the last time it was drawn.
+    pub differing_pixels: usize,
+}
+
+impl Default for OnionSkin {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            source: OverlaySource::default(),
+            offset: 0,
+            mode: BlendMode::default(),
+            opacity: 0.5,
+            differing_pixels: 0,
+        }
+    }
+}
+
+impl OnionSkin {
+    /// Draws the mode and opacity controls.
+    pub fn blend_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.mode, BlendMode::Onion, "Onion");
+            ui.radio_value(&mut self.mode, BlendMode::Difference, "Difference");
+        });
+        if self.mode == BlendMode::Onion {
+            ui.add(egui::Slider::new(&mut self.opacity, 0.0..=1.0).text("Opacity"));
+        }
+        ui.label(format!("{} pixels differ", self.differing_pixels));
+    }
+}
+
+/// Combines `overlay` into `base` in place. Both are RGBA8; the overlay is
+/// aligned to the top left and pixels outside it are left alone.
+///
+/// Returns the number of pixels of `base` that differ from the overlay,
+/// counting pixels the overlay does not cover.
+///
+/// # Arguments
+/// * `base` - The texture, `width` pixels wide.
+/// * `width` - The width of `base` in pixels.
+/// * `overlay` - The second image.
+/// * `overlay_width` - The width of `overlay` in pixels.
+/// * `mode` - How the two are combined.
+/// * `opacity` - The opacity of the overlay in `BlendMode::Onion`.
+pub fn blend(
+    base: &mut [u8],
+    width: usize,
+    overlay: &[u8],
+    overlay_width: usize,
+    mode: BlendMode,
+    opacity: f32,
+) -> usize {
+    let mut differing = 0;
+    for (i, pixel) in base.chunks_exact_mut(4).enumerate() {
+        let (x, y) = (i % width.max(1), i / width.max(1));
+        let index = (y * overlay_width + x) * 4;
+        let other = match overlay.get(index..index + 4) {
+            Some(other) if x < overlay_width => other,
+            _ => {
+                differing += 1;
+                continue;
+            }
+        };
+        if pixel != other {
+            differing += 1;
+        }
+
+        match mode {
+            BlendMode::Onion => {
+                for (channel, &other) in pixel.iter_mut().zip(other) {
+                    let mixed = *channel as f32 * (1.0 - opacity) + other as f32 * opacity;
+                    *channel = mixed.round() as u8;
+                }
+            }
+            BlendMode::Difference => {
+                for (channel, &other) in pixel.iter_mut().zip(other) {
+                    *channel = channel.abs_diff(other);
+                }
+                // Show differences in alpha as white
+                let alpha = pixel[3];
+                for channel in pixel.iter_mut().take(3) {
+                    *channel = (*channel).max(alpha);
+                }
+                pixel[3] = 0xFF;
+            }
+        }
+    }
+    differing
+}
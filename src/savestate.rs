@@ -0,0 +1,112 @@
+use std::{
+    io::{Cursor, Read},
+    path::Path,
+};
+
+use anyhow::{bail, Context, Result};
+use flate2::read::GzDecoder;
+
+/// Virtual address of the first byte of RDRAM, in KSEG0.
+pub const RDRAM_BASE: u32 = 0x8000_0000;
+
+/// Largest RDRAM size, with the Expansion Pak.
+const RDRAM_MAX_SIZE: usize = 0x80_0000;
+
+/// Magic number at the start of a Project64 savestate.
+const PJ64_MAGIC: u32 = 0x23D8_A6C8;
+/// Offset of RDRAM in a Project64 savestate, after the ROM header, CPU
+/// registers, memory-mapped registers, PIF RAM and TLB.
+const PJ64_RDRAM_OFFSET: usize = 0x75C;
+
+/// Magic string at the start of a mupen64plus savestate.
+const M64P_MAGIC: &[u8] = b"M64+SAVE";
+/// Offset of RDRAM in a mupen64plus savestate, after the header and the
+/// memory-mapped registers.
+const M64P_RDRAM_OFFSET: usize = 0x1B0;
+
+/// The emulators whose savestates can be read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Emulator {
+    Project64,
+    Mupen64Plus,
+}
+
+/// Whether a path looks like an emulator savestate, going by its extension:
+/// `.pj`, `.st`, their numbered slots (`.pj3`, `.st0`) and zipped states.
+pub fn is_savestate_path(path: &Path) -> bool {
+    let Some(extension) = path.extension() else {
+        return false;
+    };
+    let extension = extension.to_string_lossy().to_lowercase();
+    let slot = extension.trim_end_matches(|c: char| c.is_ascii_digit());
+    matches!(slot, "pj" | "st" | "zip")
+}
+
+/// Extracts RDRAM from a savestate.
+///
+/// gzip and zip containers are unwrapped first. The emulators store RDRAM
+/// as host-order 32-bit words, so each word is swapped back to big endian.
+///
+/// Returns the emulator the state came from and the RDRAM image.
+pub fn extract_rdram(bytes: &[u8]) -> Result<(Emulator, Vec<u8>)> {
+    let bytes = unwrap_container(bytes)?;
+
+    let (emulator, rdram) = if bytes.starts_with(M64P_MAGIC) {
+        let rdram = bytes
+            .get(M64P_RDRAM_OFFSET..)
+            .context("mupen64plus savestate is truncated")?;
+        (
+            Emulator::Mupen64Plus,
+            &rdram[..rdram.len().min(RDRAM_MAX_SIZE)],
+        )
+    } else if read_u32_le(&bytes, 0) == Some(PJ64_MAGIC) {
+        let size = read_u32_le(&bytes, 4).context("Project64 savestate is truncated")? as usize;
+        let rdram = bytes
+            .get(PJ64_RDRAM_OFFSET..PJ64_RDRAM_OFFSET + size)
+            .context("Project64 savestate is truncated")?;
+        (Emulator::Project64, rdram)
+    } else {
+        bail!("Not a Project64 or mupen64plus savestate");
+    };
+
+    let mut rdram = rdram.to_vec();
+    swap_words(&mut rdram);
+    Ok((emulator, rdram))
+}
+
+/// Decompresses gzip data and takes the first file out of zip archives,
+/// repeatedly, until plain data is left.
+fn unwrap_container(bytes: &[u8]) -> Result<Vec<u8>> {
+    let mut bytes = bytes.to_vec();
+    loop {
+        if bytes.starts_with(&[0x1F, 0x8B]) {
+            let mut out = vec![];
+            GzDecoder::new(bytes.as_slice())
+                .read_to_end(&mut out)
+                .context("Failed to decompress gzip savestate")?;
+            bytes = out;
+        } else if bytes.starts_with(b"PK\x03\x04") {
+            let mut archive = zip::ZipArchive::new(Cursor::new(&bytes))?;
+            let mut file = archive.by_index(0).context("Savestate archive is empty")?;
+            let mut out = vec![];
+            file.read_to_end(&mut out)?;
+            drop(file);
+            bytes = out;
+        } else {
+            return Ok(bytes);
+        }
+    }
+}
+
+/// Reverses the bytes of every 32-bit word, converting between the
+/// little-endian words emulators store and the N64's big-endian memory.
+pub fn swap_words(data: &mut [u8]) {
+    for word in data.chunks_exact_mut(4) {
+        word.reverse();
+    }
+}
+
+fn read_u32_le(bytes: &[u8], offset: usize) -> Option<u32> {
+    let word = bytes.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+}
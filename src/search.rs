@@ -0,0 +1,336 @@
+use std::ops::Range;
+
+use anyhow::{bail, Context, Result};
+use eframe::egui;
+
+/// The most hits kept, so searching for a common byte stays responsive.
+pub const MAX_HITS: usize = 10_000;
+
+/// How much is searched between progress reports in the background.
+pub const CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+/// How the search text is interpreted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchKind {
+    /// Hex bytes with `??` wildcards, e.g. `80 37 ?? 40`.
+    #[default]
+    Hex,
+    Ascii,
+    ShiftJis,
+    /// A big-endian 16-bit value at a 2-byte aligned offset.
+    U16,
+    /// A big-endian 32-bit value at a 4-byte aligned offset.
+    U32,
+}
+
+impl SearchKind {
+    pub const ALL: [SearchKind; 5] = [
+        SearchKind::Hex,
+        SearchKind::Ascii,
+        SearchKind::ShiftJis,
+        SearchKind::U16,
+        SearchKind::U32,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SearchKind::Hex => "Hex",
+            SearchKind::Ascii => "ASCII",
+            SearchKind::ShiftJis => "Shift-JIS",
+            SearchKind::U16 => "16-bit value",
+            SearchKind::U32 => "32-bit value",
+        }
+    }
+
+    /// The alignment hits must have, in bytes.
+    pub fn alignment(&self) -> usize {
+        match self {
+            SearchKind::U16 => 2,
+            SearchKind::U32 => 4,
+            _ => 1,
+        }
+    }
+}
+
+/// Parses hex bytes, separated by spaces or not, where `??` or `?` matches
+/// any byte.
+pub fn parse_hex_pattern(text: &str) -> Result<Vec<Option<u8>>> {
+    let mut pattern = vec![];
+    for token in text.split_whitespace() {
+        let token = token
+            .strip_prefix("0x")
+            .or_else(|| token.strip_prefix("0X"))
+            .unwrap_or(token);
+        if token == "?" || token == "??" {
+            pattern.push(None);
+            continue;
+        }
+        if token.len() % 2 != 0 {
+            bail!("Odd number of hex digits in \"{}\"", token);
+        }
+        for pair in token.as_bytes().chunks(2) {
+            let pair = std::str::from_utf8(pair)?;
+            if pair == "??" {
+                pattern.push(None);
+            } else {
+                let byte = u8::from_str_radix(pair, 16)
+                    .with_context(|| format!("Invalid hex byte \"{}\"", pair))?;
+                pattern.push(Some(byte));
+            }
+        }
+    }
+    Ok(pattern)
+}
+
+/// Parses a value given in hex with a `0x` prefix or in decimal, possibly
+/// negative, as `bits` bits.
+fn parse_value(text: &str, bits: u32) -> Result<u64> {
+    let text = text.trim();
+    let value = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(digits) => u64::from_str_radix(digits, 16)?,
+        None => text.parse::<i64>()? as u64,
+    };
+    let mask = (1u64 << bits) - 1;
+    let signed_fits = (value as i64) < 0 && (value as i64) >= -(1i64 << (bits - 1));
+    if value > mask && !signed_fits {
+        bail!("{} does not fit in {} bits", text, bits);
+    }
+    Ok(value & mask)
+}
+
+/// Turns the search text into a byte pattern, where `None` matches any
+/// byte.
+pub fn build_pattern(kind: SearchKind, text: &str) -> Result<Vec<Option<u8>>> {
+    let pattern: Vec<Option<u8>> = match kind {
+        SearchKind::Hex => parse_hex_pattern(text)?,
+        SearchKind::Ascii => {
+            if !text.is_ascii() {
+                bail!("Text is not ASCII");
+            }
+            text.bytes().map(Some).collect()
+        }
+        SearchKind::ShiftJis => {
+            let (bytes, _, unmappable) = encoding_rs::SHIFT_JIS.encode(text);
+            if unmappable {
+                bail!("Text cannot be encoded as Shift-JIS");
+            }
+            bytes.iter().copied().map(Some).collect()
+        }
+        SearchKind::U16 => (parse_value(text, 16)? as u16)
+            .to_be_bytes()
+            .map(Some)
+            .to_vec(),
+        SearchKind::U32 => (parse_value(text, 32)? as u32)
+            .to_be_bytes()
+            .map(Some)
+            .to_vec(),
+    };
+    if pattern.is_empty() {
+        bail!("Nothing to search for");
+    }
+    Ok(pattern)
+}
+
+/// Finds every offset in `range` where `pattern` matches, up to `MAX_HITS`.
+///
+/// # Arguments
+/// * `data` - The data to search.
+/// * `pattern` - The bytes to find; `None` matches any byte.
+/// * `range` - The part of `data` the match must lie in.
+/// * `alignment` - The alignment of hits, relative to the start of `data`.
+pub fn find_pattern(
+    data: &[u8],
+    pattern: &[Option<u8>],
+    range: Range<usize>,
+    alignment: usize,
+) -> Vec<usize> {
+    let end = range.end.min(data.len());
+    if pattern.is_empty() || range.start + pattern.len() > end {
+        return vec![];
+    }
+    let start = range.start.next_multiple_of(alignment.max(1));
+    (start..=end - pattern.len())
+        .step_by(alignment.max(1))
+        .filter(|&offset| {
+            pattern
+                .iter()
+                .zip(&data[offset..])
+                .all(|(p, &b)| p.is_none_or(|p| p == b))
+        })
+        .take(MAX_HITS)
+        .collect()
+}
+
+/// Like `find_pattern`, but searches `range` in chunks of `chunk_size`
+/// bytes and reports the fraction searched to `progress` before each chunk.
+/// The search stops early, keeping the hits so far, when `progress` returns
+/// `false`.
+pub fn find_pattern_with_progress(
+    data: &[u8],
+    pattern: &[Option<u8>],
+    range: Range<usize>,
+    alignment: usize,
+    chunk_size: usize,
+    mut progress: impl FnMut(f32) -> bool,
+) -> Vec<usize> {
+    let end = range.end.min(data.len());
+    let chunk_size = chunk_size.max(1);
+    let mut hits = vec![];
+    let mut start = range.start;
+    while start < end && hits.len() < MAX_HITS {
+        if !progress((start - range.start) as f32 / (end - range.start) as f32) {
+            break;
+        }
+        let chunk_end = (start + chunk_size).min(end);
+        // Let matches that start in this chunk run past its end
+        let search_end = (chunk_end + pattern.len().saturating_sub(1)).min(end);
+        let chunk_hits = find_pattern(data, pattern, start..search_end, alignment);
+        hits.extend(chunk_hits.into_iter().filter(|&hit| hit < chunk_end));
+        start = chunk_end;
+    }
+    hits.truncate(MAX_HITS);
+    hits
+}
+
+/// The state of the search window.
+#[derive(Debug, Default)]
+pub struct Search {
+    pub kind: SearchKind,
+    pub text: String,
+    /// Whether to search only `range_start..range_end`.
+    pub limit_range: bool,
+    pub range_start: usize,
+    pub range_end: usize,
+    pub hits: Vec<usize>,
+    pub error: Option<String>,
+}
+
+impl Search {
+    /// Clears the hits and builds the pattern and range to search a file
+    /// of `len` bytes with, so the search can run elsewhere.
+    ///
+    /// Returns `None` and sets `error` if the search text is invalid.
+    pub fn prepare(&mut self, len: usize) -> Option<(Vec<Option<u8>>, Range<usize>)> {
+        self.hits.clear();
+        self.error = None;
+        let range = if self.limit_range {
+            self.range_start..self.range_end
+        } else {
+            0..len
+        };
+        match build_pattern(self.kind, &self.text) {
+            Ok(pattern) => Some((pattern, range)),
+            Err(e) => {
+                self.error = Some(e.to_string());
+                None
+            }
+        }
+    }
+
+    /// The first hit after `pos`, wrapping around to the first hit.
+    pub fn next_hit(&self, pos: usize) -> Option<usize> {
+        let index = self.hits.partition_point(|&hit| hit <= pos);
+        self.hits.get(index).or(self.hits.first()).copied()
+    }
+
+    /// The last hit before `pos`, wrapping around to the last hit.
+    pub fn previous_hit(&self, pos: usize) -> Option<usize> {
+        let index = self.hits.partition_point(|&hit| hit < pos);
+        match index {
+            0 => self.hits.last().copied(),
+            i => Some(self.hits[i - 1]),
+        }
+    }
+}
+
+/// What the user asked for in the search window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchRequest {
+    /// Run the search.
+    Run,
+    /// Jump to a hit.
+    Jump(usize),
+}
+
+/// Displays the search window.
+///
+/// Searching is left to the caller, so it can run in the background.
+///
+/// # Arguments
+/// * `ctx` - The egui context
+/// * `show` - Mutable reference to control window visibility
+/// * `search` - The search state
+/// * `searching` - Whether a search is running; the Find button is disabled
+/// * `file_pos` - The current position, highlighted in the hit list
+pub fn search_window(
+    ctx: &egui::Context,
+    show: &mut bool,
+    search: &mut Search,
+    searching: bool,
+    file_pos: usize,
+) -> Option<SearchRequest> {
+    let mut request = None;
+    egui::Window::new("Search").open(show).show(ctx, |ui| {
+        let mut run = false;
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("search_kind")
+                .selected_text(search.kind.name())
+                .show_ui(ui, |ui| {
+                    for kind in SearchKind::ALL {
+                        ui.selectable_value(&mut search.kind, kind, kind.name());
+                    }
+                });
+            let response = ui.text_edit_singleline(&mut search.text);
+            run = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            run |= ui
+                .add_enabled(!searching, egui::Button::new("Find"))
+                .clicked();
+            run &= !searching;
+        });
+
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut search.limit_range, "Range:");
+            ui.add_enabled_ui(search.limit_range, |ui| {
+                ui.add(egui::DragValue::new(&mut search.range_start).hexadecimal(8, false, true));
+                ui.label("to");
+                ui.add(egui::DragValue::new(&mut search.range_end).hexadecimal(8, false, true));
+            });
+        });
+
+        if run {
+            request = Some(SearchRequest::Run);
+        }
+
+        if let Some(error) = &search.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+        if searching {
+            ui.label("Searching...");
+        }
+        let capped = if search.hits.len() == MAX_HITS {
+            " (stopped at the limit)"
+        } else {
+            ""
+        };
+        ui.label(format!(
+            "{} hits{}, F3 / Shift+F3 to cycle",
+            search.hits.len(),
+            capped
+        ));
+        ui.separator();
+
+        let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+        egui::ScrollArea::vertical()
+            .id_salt("search_hits")
+            .max_height(300.0)
+            .show_rows(ui, row_height, search.hits.len(), |ui, rows| {
+                for &hit in &search.hits[rows] {
+                    let text = egui::RichText::new(format!("0x{:08X}", hit)).monospace();
+                    if ui.selectable_label(hit == file_pos, text).clicked() {
+                        request = Some(SearchRequest::Jump(hit));
+                    }
+                }
+            });
+    });
+    request
+}
@@ -0,0 +1,290 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use eframe::egui;
+
+/// The number of segments the RSP can address.
+pub const NUM_SEGMENTS: usize = 16;
+
+/// Where the bytes of a segment live.
+///
+/// Only files are supported; blocks that exist only in memory, such as
+/// decompressed data, cannot back a segment yet.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SegmentSource {
+    /// The file that is currently open in motex.
+    OpenFile,
+    /// A separate file on disk, opened when an address inside it is followed.
+    External(PathBuf),
+}
+
+/// A single segment table entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    /// The offset into the source at which the segment starts.
+    pub base: usize,
+    /// The data backing the segment.
+    pub source: SegmentSource,
+}
+
+/// The result of resolving an address through the segment table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedAddress<'a> {
+    /// The segment the address belongs to.
+    pub segment: u8,
+    /// The data backing the segment.
+    pub source: &'a SegmentSource,
+    /// The absolute offset into the source.
+    pub offset: usize,
+}
+
+/// Maps segment numbers to base offsets, mirroring the RSP segment table
+/// that display lists are executed against.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentTable {
+    segments: [Option<Segment>; NUM_SEGMENTS],
+}
+
+impl Default for SegmentTable {
+    /// Segment 0 maps to the start of the open file so that plain file
+    /// offsets resolve to themselves.
+    fn default() -> Self {
+        let mut segments: [Option<Segment>; NUM_SEGMENTS] = Default::default();
+        segments[0] = Some(Segment {
+            base: 0,
+            source: SegmentSource::OpenFile,
+        });
+        Self { segments }
+    }
+}
+
+impl SegmentTable {
+    /// Returns the entry for the given segment, if it is mapped.
+    pub fn get(&self, segment: u8) -> Option<&Segment> {
+        self.segments.get(segment as usize)?.as_ref()
+    }
+
+    /// Maps a segment, replacing any existing entry.
+    ///
+    /// # Arguments
+    /// * `segment` - The segment number, 0 through 15.
+    /// * `base` - The offset into `source` at which the segment starts.
+    /// * `source` - The data backing the segment.
+    pub fn set(&mut self, segment: u8, base: usize, source: SegmentSource) {
+        if let Some(entry) = self.segments.get_mut(segment as usize) {
+            *entry = Some(Segment { base, source });
+        }
+    }
+
+    /// Removes the mapping for a segment.
+    pub fn clear(&mut self, segment: u8) {
+        if let Some(entry) = self.segments.get_mut(segment as usize) {
+            *entry = None;
+        }
+    }
+
+    /// Resolves a segmented address (`0xSSOOOOOO`) to an offset into the
+    /// segment's source.
+    ///
+    /// Returns `None` if the upper nibble is set or the segment is unmapped.
+    pub fn resolve(&self, addr: u32) -> Option<ResolvedAddress<'_>> {
+        if addr >> 28 != 0 {
+            return None;
+        }
+
+        let segment = (addr >> 24) as u8;
+        let entry = self.get(segment)?;
+        Some(ResolvedAddress {
+            segment,
+            source: &entry.source,
+            offset: entry.base + (addr & 0x00FF_FFFF) as usize,
+        })
+    }
+
+    /// Finds a segmented address for an offset into the open file.
+    ///
+    /// Segment 0 is only used as a last resort since every offset trivially
+    /// resolves through it. Of the remaining segments, the one with the
+    /// closest base below `offset` wins.
+    pub fn to_segmented(&self, offset: usize) -> Option<u32> {
+        self.segments
+            .iter()
+            .enumerate()
+            .filter_map(|(segment, entry)| {
+                let entry = entry.as_ref()?;
+                if entry.source != SegmentSource::OpenFile || offset < entry.base {
+                    return None;
+                }
+                let rel = offset - entry.base;
+                (rel <= 0x00FF_FFFF).then_some((segment, rel))
+            })
+            .min_by_key(|&(segment, rel)| (segment == 0, rel))
+            .map(|(segment, rel)| ((segment as u32) << 24) | rel as u32)
+    }
+}
+
+/// Every byte reachable through a segment table, used when following
+/// addresses found inside the data itself.
+pub struct AddressSpace<'a> {
+    segments: &'a SegmentTable,
+    data: &'a [u8],
+    external: HashMap<PathBuf, Vec<u8>>,
+    /// Virtual address of the open file's first byte, or zero.
+    base_address: u32,
+}
+
+impl<'a> AddressSpace<'a> {
+    /// Creates an address space over the open file's data.
+    ///
+    /// External segment sources are read up front. Files that cannot be read
+    /// are skipped, leaving their segments unresolvable.
+    pub fn new(segments: &'a SegmentTable, data: &'a [u8]) -> Self {
+        let external = segments
+            .segments
+            .iter()
+            .flatten()
+            .filter_map(|entry| match &entry.source {
+                SegmentSource::External(path) => {
+                    let bytes = std::fs::read(path).ok()?;
+                    Some((path.clone(), bytes))
+                }
+                SegmentSource::OpenFile => None,
+            })
+            .collect();
+
+        Self {
+            segments,
+            data,
+            external,
+            base_address: 0,
+        }
+    }
+
+    /// Maps the open file at a virtual address, so addresses such as
+    /// `0x80xxxxxx` into an RDRAM image resolve without a segment.
+    pub fn with_base_address(mut self, base_address: u32) -> Self {
+        self.base_address = base_address;
+        self
+    }
+
+    /// The segment table addresses are resolved through.
+    pub fn segments(&self) -> &SegmentTable {
+        self.segments
+    }
+
+    /// Returns the bytes from `addr` to the end of its source.
+    pub fn slice(&self, addr: u32) -> Option<&[u8]> {
+        if self.base_address != 0 {
+            if let Some(offset) = addr.checked_sub(self.base_address) {
+                if (offset as usize) < self.data.len() {
+                    return Some(&self.data[offset as usize..]);
+                }
+            }
+        }
+
+        let resolved = self.segments.resolve(addr)?;
+        let source = match resolved.source {
+            SegmentSource::OpenFile => self.data,
+            SegmentSource::External(path) => self.external.get(path)?.as_slice(),
+        };
+        source.get(resolved.offset..)
+    }
+}
+
+/// Parses a hexadecimal address, with or without a `0x` prefix.
+pub fn parse_address(text: &str) -> Option<u32> {
+    let text = text.trim();
+    let digits = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .unwrap_or(text);
+    u32::from_str_radix(digits, 16).ok()
+}
+
+/// Draws an address as a clickable link.
+///
+/// The hover text shows where the address resolves to.
+/// Returns `true` if the link was clicked.
+pub fn address_link(ui: &mut egui::Ui, segments: &SegmentTable, addr: u32) -> bool {
+    let hover = match segments.resolve(addr) {
+        Some(resolved) => match resolved.source {
+            SegmentSource::OpenFile => format!("File offset 0x{:08X}", resolved.offset),
+            SegmentSource::External(path) => {
+                format!("{} + 0x{:08X}", path.display(), resolved.offset)
+            }
+        },
+        None => "Unmapped segment".to_owned(),
+    };
+
+    ui.link(egui::RichText::new(format!("0x{:08X}", addr)).monospace())
+        .on_hover_text(hover)
+        .clicked()
+}
+
+/// Displays the segment table editor.
+///
+/// # Arguments
+/// * `ctx` - The egui context
+/// * `show` - Mutable reference to control window visibility
+/// * `segments` - The segment table to edit
+pub fn segment_window(ctx: &egui::Context, show: &mut bool, segments: &mut SegmentTable) {
+    egui::Window::new("Segment Table")
+        .open(show)
+        .show(ctx, |ui| {
+            egui::Grid::new("segment_grid")
+                .num_columns(4)
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("Seg");
+                    ui.label("Base");
+                    ui.label("Source");
+                    ui.label("");
+                    ui.end_row();
+
+                    for segment in 0..NUM_SEGMENTS as u8 {
+                        ui.monospace(format!("{:02X}", segment));
+
+                        match segments.segments[segment as usize].as_mut() {
+                            Some(entry) => {
+                                ui.add(
+                                    egui::DragValue::new(&mut entry.base)
+                                        .hexadecimal(8, false, true)
+                                        .prefix("0x"),
+                                );
+
+                                ui.horizontal(|ui| {
+                                    let is_external =
+                                        matches!(entry.source, SegmentSource::External(_));
+                                    if ui.selectable_label(!is_external, "Open file").clicked() {
+                                        entry.source = SegmentSource::OpenFile;
+                                    }
+                                    let label = match &entry.source {
+                                        SegmentSource::External(path) => path
+                                            .file_name()
+                                            .map(|name| name.to_string_lossy().into_owned())
+                                            .unwrap_or_default(),
+                                        SegmentSource::OpenFile => "External...".to_owned(),
+                                    };
+                                    if ui.selectable_label(is_external, label).clicked() {
+                                        if let Some(path) = rfd::FileDialog::new().pick_file() {
+                                            entry.source = SegmentSource::External(path);
+                                        }
+                                    }
+                                });
+
+                                if ui.button("Clear").clicked() {
+                                    segments.clear(segment);
+                                }
+                            }
+                            None => {
+                                ui.label("-");
+                                ui.label("Unmapped");
+                                if ui.button("Map").clicked() {
+                                    segments.set(segment, 0, SegmentSource::OpenFile);
+                                }
+                            }
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+}
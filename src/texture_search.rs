@@ -0,0 +1,291 @@
+use std::io::Cursor;
+
+use anyhow::Result;
+use pigment64::{ImageType, PNGImage};
+use strum::IntoEnumIterator;
+
+use crate::{export::encode_png, texview::bpp_from_image_type};
+
+/// The most hits reported per format, so flat images that match every run
+/// of equal bytes do not flood the list.
+pub const MAX_HITS: usize = 256;
+
+/// The number of offsets tried between progress reports.
+pub const PROGRESS_INTERVAL: usize = 1 << 20;
+
+/// A place in the file where the searched image was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextureHit {
+    pub offset: usize,
+    pub format: ImageType,
+}
+
+/// Encodes an RGBA8 image to a non-CI N64 format through pigment64.
+///
+/// # Arguments
+/// * `width` - The width of the image in pixels.
+/// * `height` - The height of the image in pixels.
+/// * `rgba` - The image.
+/// * `format` - The format to encode to; CI formats are not supported.
+pub fn encode_texture(
+    width: usize,
+    height: usize,
+    rgba: &[u8],
+    format: ImageType,
+) -> Result<Vec<u8>> {
+    let png = PNGImage::read(Cursor::new(encode_png(width, height, rgba)?))?;
+    let mut out = vec![];
+    png.as_native(&mut out, format)?;
+    Ok(out)
+}
+
+/// Reads the `index`th pixel of `bits`-bit encoded data.
+fn pixel_value(data: &[u8], bits: usize, index: usize) -> u32 {
+    let bit = index * bits;
+    if bits < 8 {
+        let shift = 8 - bits - bit % 8;
+        (data[bit / 8] as u32 >> shift) & ((1 << bits) - 1)
+    } else {
+        let start = bit / 8;
+        data[start..start + bits / 8]
+            .iter()
+            .fold(0, |acc, &b| (acc << 8) | b as u32)
+    }
+}
+
+/// Splits an encoded pixel into its quantized channels, so values can be
+/// compared within a number of quantization steps.
+fn channels(format: ImageType, v: u32) -> [u8; 4] {
+    match format {
+        ImageType::Rgba16 => [
+            (v >> 11) as u8 & 0x1F,
+            (v >> 6) as u8 & 0x1F,
+            (v >> 1) as u8 & 0x1F,
+            v as u8 & 1,
+        ],
+        ImageType::Rgba32 => v.to_be_bytes(),
+        ImageType::Ia4 => [(v >> 1) as u8, 0, 0, v as u8 & 1],
+        ImageType::Ia8 => [(v >> 4) as u8, 0, 0, v as u8 & 0xF],
+        ImageType::Ia16 => [(v >> 8) as u8, 0, 0, v as u8],
+        _ => [v as u8, 0, 0, 0],
+    }
+}
+
+/// Whether the pixels of `candidate` are all within `tolerance` quantization
+/// steps of `expected` in every channel.
+fn matches_within(
+    candidate: &[u8],
+    expected: &[u8],
+    format: ImageType,
+    pixels: usize,
+    tolerance: u8,
+) -> bool {
+    let bits = (bpp_from_image_type(format) * 8.0) as usize;
+    (0..pixels).all(|i| {
+        let a = channels(format, pixel_value(candidate, bits, i));
+        let b = channels(format, pixel_value(expected, bits, i));
+        a.iter().zip(b).all(|(&a, b)| a.abs_diff(b) <= tolerance)
+    })
+}
+
+/// Finds every offset where `expected` is stored, exactly or within
+/// `tolerance` quantization steps per channel.
+///
+/// # Arguments
+/// * `data` - The data to search.
+/// * `expected` - The encoded image.
+/// * `format` - The format `expected` is encoded in.
+/// * `pixels` - The number of pixels in the image.
+/// * `tolerance` - The allowed difference per channel; 0 for an exact match.
+/// * `progress` - Called with the fraction of `data` searched every
+///   `PROGRESS_INTERVAL` offsets; the search stops, keeping the hits so far,
+///   when it returns `false`.
+pub fn find_encoded(
+    data: &[u8],
+    expected: &[u8],
+    format: ImageType,
+    pixels: usize,
+    tolerance: u8,
+    mut progress: impl FnMut(f32) -> bool,
+) -> Vec<usize> {
+    if expected.is_empty() || expected.len() > data.len() {
+        return vec![];
+    }
+    let mut hits = vec![];
+    for (offset, window) in data.windows(expected.len()).enumerate() {
+        if offset % PROGRESS_INTERVAL == 0 && !progress(offset as f32 / data.len() as f32) {
+            break;
+        }
+        let matched = if tolerance == 0 {
+            window == expected
+        } else {
+            matches_within(window, expected, format, pixels, tolerance)
+        };
+        if matched {
+            hits.push(offset);
+            if hits.len() == MAX_HITS {
+                break;
+            }
+        }
+    }
+    hits
+}
+
+/// Numbers the distinct colors of an RGBA8 image in the order they first
+/// appear.
+///
+/// Returns `None` if there are more than `max_colors` colors.
+pub fn color_labels(rgba: &[u8], max_colors: usize) -> Option<Vec<u8>> {
+    let mut colors: Vec<&[u8]> = vec![];
+    let mut labels = Vec::with_capacity(rgba.len() / 4);
+    for pixel in rgba.chunks_exact(4) {
+        let label = match colors.iter().position(|&c| c == pixel) {
+            Some(label) => label,
+            None => {
+                colors.push(pixel);
+                colors.len() - 1
+            }
+        };
+        if label >= max_colors {
+            return None;
+        }
+        labels.push(label as u8);
+    }
+    Some(labels)
+}
+
+/// Finds CI data whose indices follow the same pattern as `labels`, whatever
+/// the palette order: two pixels share an index exactly when they share a
+/// label.
+///
+/// # Arguments
+/// * `data` - The data to search.
+/// * `labels` - The colors of the image, numbered by `color_labels`.
+/// * `format` - `Ci4` or `Ci8`.
+/// * `progress` - Called with the fraction of `data` searched every
+///   `PROGRESS_INTERVAL` offsets; the search stops, keeping the hits so far,
+///   when it returns `false`.
+pub fn find_ci_pattern(
+    data: &[u8],
+    labels: &[u8],
+    format: ImageType,
+    mut progress: impl FnMut(f32) -> bool,
+) -> Vec<usize> {
+    let bits = (bpp_from_image_type(format) * 8.0) as usize;
+    let len = (labels.len() * bits).div_ceil(8);
+    if labels.is_empty() || len > data.len() {
+        return vec![];
+    }
+
+    let mut to_label = [u16::MAX; 256];
+    let mut to_index = [u16::MAX; 256];
+    let mut touched = vec![];
+    let mut hits = vec![];
+    for offset in 0..=data.len() - len {
+        if offset % PROGRESS_INTERVAL == 0 && !progress(offset as f32 / data.len() as f32) {
+            break;
+        }
+        let window = &data[offset..offset + len];
+        let matched = labels.iter().enumerate().all(|(i, &label)| {
+            let index = pixel_value(window, bits, i) as usize;
+            match (to_label[index], to_index[label as usize]) {
+                (u16::MAX, u16::MAX) => {
+                    to_label[index] = label as u16;
+                    to_index[label as usize] = index as u16;
+                    touched.push((index, label as usize));
+                    true
+                }
+                (l, x) => l == label as u16 && x == index as u16,
+            }
+        });
+        for (index, label) in touched.drain(..) {
+            to_label[index] = u16::MAX;
+            to_index[label] = u16::MAX;
+        }
+        if matched {
+            hits.push(offset);
+            if hits.len() == MAX_HITS {
+                break;
+            }
+        }
+    }
+    hits
+}
+
+/// Searches `data` for an RGBA8 image in every N64 format.
+///
+/// Non-CI formats are encoded through pigment64 and searched for directly.
+/// CI formats are matched by their index pattern, since the palette order
+/// is unknown.
+///
+/// # Arguments
+/// * `data` - The data to search.
+/// * `width` - The width of the image in pixels.
+/// * `height` - The height of the image in pixels.
+/// * `rgba` - The image.
+/// * `tolerance` - The allowed difference per channel, in quantization
+///   steps of each format; 0 for an exact match.
+pub fn find_texture(
+    data: &[u8],
+    width: usize,
+    height: usize,
+    rgba: &[u8],
+    tolerance: u8,
+) -> Result<Vec<TextureHit>> {
+    find_texture_with_progress(data, width, height, rgba, tolerance, |_| true)
+}
+
+/// Like `find_texture`, but reports the fraction searched to `progress`
+/// before each format and every `PROGRESS_INTERVAL` offsets within one. The
+/// search stops early, keeping the hits so far, when `progress` returns
+/// `false`.
+pub fn find_texture_with_progress(
+    data: &[u8],
+    width: usize,
+    height: usize,
+    rgba: &[u8],
+    tolerance: u8,
+    mut progress: impl FnMut(f32) -> bool,
+) -> Result<Vec<TextureHit>> {
+    let mut hits = vec![];
+    let formats: Vec<ImageType> = ImageType::iter().collect();
+    for (i, &format) in formats.iter().enumerate() {
+        let mut cancelled = !progress(i as f32 / formats.len() as f32);
+        if cancelled {
+            break;
+        }
+        let mut format_progress = |fraction: f32| {
+            cancelled = !progress((i as f32 + fraction) / formats.len() as f32);
+            !cancelled
+        };
+        let offsets = match format {
+            ImageType::Ci4 | ImageType::Ci8 => {
+                let max_colors = format.get_size().get_tlut_size();
+                match color_labels(rgba, max_colors) {
+                    Some(labels) => find_ci_pattern(data, &labels, format, &mut format_progress),
+                    None => vec![],
+                }
+            }
+            _ => {
+                let expected = encode_texture(width, height, rgba, format)?;
+                find_encoded(
+                    data,
+                    &expected,
+                    format,
+                    width * height,
+                    tolerance,
+                    &mut format_progress,
+                )
+            }
+        };
+        hits.extend(
+            offsets
+                .into_iter()
+                .map(|offset| TextureHit { offset, format }),
+        );
+        if cancelled {
+            break;
+        }
+    }
+    Ok(hits)
+}
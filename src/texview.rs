@@ -1,7 +1,190 @@
-use std::iter;
+use std::{iter, ops::Range};
 
 use eframe::egui::{self, Color32, ColorImage, Sense, TextureHandle, TextureOptions};
-use pigment64::{ImageType, NativeImage};
+use pigment64::{image::native_image::parse_tlut, ImageType, NativeImage, TextureLUT};
+
+use crate::{
+    combiner::Combiner,
+    display_list::TileDescriptor,
+    onion::{blend, OnionImage, OverlayPixels},
+};
+
+/// Reverses the byte order within fixed-size words, for data dumped from
+/// little-endian hosts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ByteSwap {
+    #[default]
+    None,
+    Swap16,
+    Swap32,
+    Swap64,
+}
+
+impl ByteSwap {
+    pub const ALL: [ByteSwap; 4] = [
+        ByteSwap::None,
+        ByteSwap::Swap16,
+        ByteSwap::Swap32,
+        ByteSwap::Swap64,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ByteSwap::None => "None",
+            ByteSwap::Swap16 => "Swap16",
+            ByteSwap::Swap32 => "Swap32",
+            ByteSwap::Swap64 => "Swap64",
+        }
+    }
+
+    /// The size of the words that are swapped, in bytes.
+    pub fn word_size(&self) -> usize {
+        match self {
+            ByteSwap::None => 1,
+            ByteSwap::Swap16 => 2,
+            ByteSwap::Swap32 => 4,
+            ByteSwap::Swap64 => 8,
+        }
+    }
+
+    /// Returns `len` bytes of `data` starting at `offset`, with words
+    /// swapped. Words are aligned to the start of `data`, not to `offset`,
+    /// so the result does not change with where the view starts. A partial
+    /// word at the end of `data` is dropped.
+    pub fn read(&self, data: &[u8], offset: usize, len: usize) -> Vec<u8> {
+        let word = self.word_size();
+        let start = offset - offset % word;
+        let end = (offset + len).next_multiple_of(word).min(data.len());
+        // A partial word at the end of the data cannot be swapped
+        let end = end - end.saturating_sub(start) % word;
+        if end <= offset {
+            return vec![];
+        }
+        let bytes = &data[start..end];
+
+        let mut swapped = bytes.to_vec();
+        for chunk in swapped.chunks_exact_mut(word) {
+            chunk.reverse();
+        }
+        let mut swapped = swapped.split_off(offset - start);
+        swapped.truncate(len);
+        swapped
+    }
+}
+
+/// Guides drawn over a zoomed-in texture to check width and offset
+/// alignment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Overlays {
+    /// A line between every pixel.
+    pub pixel_grid: bool,
+    /// The size of the block grid in pixels, or 0 for none.
+    pub block_size: usize,
+    /// A line at every 8-byte TMEM word along each row.
+    pub tmem_words: bool,
+    /// Lines through the hovered pixel.
+    pub crosshair: bool,
+}
+
+/// The zoom range of the texture viewport.
+pub const MIN_ZOOM: f32 = 0.125;
+pub const MAX_ZOOM: f32 = 64.0;
+
+/// The smallest zoom at which per-pixel lines are drawn.
+const MIN_GRID_ZOOM: f32 = 4.0;
+
+/// Everything a decoded image depends on besides the file contents, which
+/// are covered by the generation of the file.
+#[derive(Debug, Clone, PartialEq)]
+struct DecodeKey {
+    generation: u64,
+    offset: usize,
+    format: ImageType,
+    width: usize,
+    height: usize,
+    palette: Option<Vec<u8>>,
+    tiled: bool,
+    tile_repeats: usize,
+    tile: TileDescriptor,
+    combiner: Combiner,
+    stride: Option<usize>,
+    swap: ByteSwap,
+}
+
+impl DecodeKey {
+    fn new(view: &TexView, generation: u64, offset: usize) -> Self {
+        Self {
+            generation,
+            offset,
+            format: view.format,
+            width: view.width,
+            height: view.height,
+            palette: view.palette.clone(),
+            tiled: view.tiled,
+            tile_repeats: view.tile_repeats,
+            tile: view.tile,
+            combiner: view.combiner,
+            stride: view.stride,
+            swap: view.swap,
+        }
+    }
+
+    /// Compares against the view's current settings without cloning them.
+    fn matches(&self, view: &TexView, generation: u64, offset: usize) -> bool {
+        self.generation == generation
+            && self.offset == offset
+            && self.format == view.format
+            && self.width == view.width
+            && self.height == view.height
+            && self.palette == view.palette
+            && self.tiled == view.tiled
+            && self.tile_repeats == view.tile_repeats
+            && self.tile == view.tile
+            && self.combiner == view.combiner
+            && self.stride == view.stride
+            && self.swap == view.swap
+    }
+}
+
+/// Everything a shown image depends on: the decoded image and what is drawn
+/// over it.
+#[derive(Debug, Clone, PartialEq)]
+struct RenderKey {
+    decode: DecodeKey,
+    highlights: Vec<Range<usize>>,
+    onion: Option<OnionImage>,
+}
+
+impl RenderKey {
+    fn new(view: &TexView, generation: u64, offset: usize) -> Self {
+        Self {
+            decode: DecodeKey::new(view, generation, offset),
+            highlights: view.highlights.clone(),
+            onion: view.onion.clone(),
+        }
+    }
+
+    /// Compares against the view's current settings without cloning them.
+    fn matches(&self, view: &TexView, generation: u64, offset: usize) -> bool {
+        self.decode.matches(view, generation, offset)
+            && self.highlights == view.highlights
+            && self.onion == view.onion
+    }
+}
+
+/// A rendered image as uploaded to the GPU.
+struct RenderCache {
+    key: RenderKey,
+    width: usize,
+    height: usize,
+    rgba: Vec<u8>,
+}
+
+/// The last image decoded by `TexView::render_overlay`.
+struct OverlayCache {
+    key: DecodeKey,
+    pixels: OverlayPixels,
+}
 
 pub struct TexView {
     pub format: ImageType,
@@ -12,6 +195,43 @@ pub struct TexView {
     pub bg_tex: TextureHandle,
     pub tex: TextureHandle,
     pub hover_color: Option<Color32>,
+    /// Raw RGBA16 palette used to decode CI formats.
+    pub palette: Option<Vec<u8>>,
+    /// Whether to draw the texture repeated according to `tile`.
+    pub tiled: bool,
+    /// The number of times the texture is repeated in each direction when
+    /// tiled.
+    pub tile_repeats: usize,
+    /// Wrap, mirror, clamp, mask and shift settings for the tiled preview.
+    pub tile: TileDescriptor,
+    /// The color combiner setup the texture is previewed through.
+    pub combiner: Combiner,
+    /// The distance between rows in pixels, if it differs from `width`, as
+    /// with framebuffers drawn into a wider pitch.
+    pub stride: Option<usize>,
+    /// Byte order transform applied to the data before decoding.
+    pub swap: ByteSwap,
+    /// Guides drawn over the texture.
+    pub overlays: Overlays,
+    /// Sorted byte ranges of the file whose pixels are tinted, such as the
+    /// differences from a compared file.
+    pub highlights: Vec<Range<usize>>,
+    /// A second image blended over the drawn texture.
+    pub onion: Option<OnionImage>,
+    /// The number of pixels that differed from `onion` on the last draw.
+    pub onion_differing: usize,
+    /// The pixel under the cursor in the drawn image, if any.
+    pub hovered_pixel: Option<(usize, usize)>,
+    /// Offset of the image from the top left of the viewport, in points.
+    pub pan: egui::Vec2,
+    /// The last rendered image, reused while nothing it depends on changes.
+    cache: Option<RenderCache>,
+    /// The last image decoded for another view's onion skin.
+    overlay_cache: Option<OverlayCache>,
+    /// Whether to fit the texture to the viewport on the next draw.
+    fit_pending: bool,
+    /// The size of the viewport when it was last drawn.
+    viewport_size: egui::Vec2,
 }
 
 impl TexView {
@@ -33,14 +253,147 @@ impl TexView {
                 Default::default(),
             ),
             hover_color: Some(Color32::from_rgba_premultiplied(0, 0, 0, 0)),
+            palette: None,
+            tiled: false,
+            tile_repeats: 3,
+            tile: TileDescriptor::default(),
+            combiner: Combiner::default(),
+            stride: None,
+            swap: ByteSwap::default(),
+            overlays: Overlays::default(),
+            highlights: vec![],
+            onion: None,
+            onion_differing: 0,
+            hovered_pixel: None,
+            pan: egui::Vec2::ZERO,
+            cache: None,
+            overlay_cache: None,
+            fit_pending: false,
+            viewport_size: egui::Vec2::ZERO,
         }
     }
 
-    pub fn draw(&mut self, data: &[u8], offset: usize, ui: &mut egui::Ui, ctx: &egui::Context) {
+    /// Draws the texture at its zoomed size.
+    ///
+    /// Returns the response of the image, which senses clicks.
+    ///
+    /// # Arguments
+    /// * `data` - The whole file.
+    /// * `generation` - The `BinFile::generation` of `data`.
+    /// * `offset` - The offset of the first pixel.
+    /// * `ui` - The egui ui to draw into.
+    /// * `ctx` - The egui context.
+    pub fn draw(
+        &mut self,
+        data: &[u8],
+        generation: u64,
+        offset: usize,
+        ui: &mut egui::Ui,
+        ctx: &egui::Context,
+    ) -> Option<egui::Response> {
+        if offset > data.len() {
+            return None;
+        }
+
+        let cache = self.upload(data, generation, offset);
+        let (width, height) = (cache.width, cache.height);
+
+        // Apply zoom to the texture size
+        let zoomed_size = egui::vec2(width as f32 * self.zoom, height as f32 * self.zoom);
+
+        // Create a group to contain the image
+        let response = egui::Frame::none().fill(self.bg_color).show(ui, |ui| {
+            // Use a fixed size area that matches our zoomed dimensions
+            let (res, painter) = ui.allocate_painter(zoomed_size, Sense::click());
+            self.paint(&painter, res.rect, width, height, &cache.rgba, ctx);
+            res
+        });
+        self.cache = Some(cache);
+        Some(response.inner)
+    }
+
+    /// Draws the texture into all of the available space, with Ctrl+wheel
+    /// zoom around the cursor and drag to pan.
+    ///
+    /// # Arguments
+    /// * `data` - The whole file.
+    /// * `generation` - The `BinFile::generation` of `data`.
+    /// * `offset` - The offset of the first pixel.
+    /// * `ui` - The egui ui to draw into.
+    /// * `ctx` - The egui context.
+    pub fn draw_viewport(
+        &mut self,
+        data: &[u8],
+        generation: u64,
+        offset: usize,
+        ui: &mut egui::Ui,
+        ctx: &egui::Context,
+    ) {
         if offset > data.len() {
             return;
         }
 
+        let cache = self.upload(data, generation, offset);
+        let (width, height) = (cache.width, cache.height);
+        let image_size = egui::vec2(width as f32, height as f32);
+
+        let (rect, response) = ui.allocate_exact_size(ui.available_size(), Sense::drag());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, self.bg_color);
+        self.viewport_size = rect.size();
+
+        if self.fit_pending {
+            self.fit_pending = false;
+            let fit = (rect.size() / image_size).min_elem();
+            self.zoom = fit.clamp(MIN_ZOOM, MAX_ZOOM);
+            self.pan = (rect.size() - image_size * self.zoom) / 2.0;
+        }
+
+        if response.dragged() {
+            self.pan += response.drag_delta();
+        }
+        if let Some(cursor) = response.hover_pos() {
+            let zoom_delta = ui.input(|i| i.zoom_delta());
+            if zoom_delta != 1.0 {
+                self.zoom_around(self.zoom * zoom_delta, cursor - rect.min);
+            }
+        }
+
+        let image_rect = egui::Rect::from_min_size(rect.min + self.pan, image_size * self.zoom);
+        self.paint(&painter, image_rect, width, height, &cache.rgba, ctx);
+        self.cache = Some(cache);
+    }
+
+    /// Changes the zoom while keeping the image point under `anchor`, relative
+    /// to the viewport, in place.
+    pub fn zoom_around(&mut self, zoom: f32, anchor: egui::Vec2) {
+        let zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+        self.pan = anchor - (anchor - self.pan) * (zoom / self.zoom);
+        self.zoom = zoom;
+    }
+
+    /// Multiplies the zoom, keeping the center of the viewport in place.
+    pub fn zoom_by(&mut self, factor: f32) {
+        self.zoom_around(self.zoom * factor, self.viewport_size / 2.0);
+    }
+
+    /// Fits the texture to the viewport the next time it is drawn.
+    pub fn fit(&mut self) {
+        self.fit_pending = true;
+    }
+
+    /// Renders the texture and uploads it to the GPU, unless the cached
+    /// image is still current.
+    ///
+    /// The cache is taken out of the view so it can be read while the view
+    /// is painted; callers put it back.
+    fn upload(&mut self, data: &[u8], generation: u64, offset: usize) -> RenderCache {
+        if let Some(cache) = self.cache.take() {
+            if cache.key.matches(self, generation, offset) {
+                return cache;
+            }
+        }
+
         let siz: usize = self.width * self.height * 4;
 
         // Create a black background
@@ -53,19 +406,24 @@ impl TexView {
             TextureOptions::NEAREST, // Use nearest neighbor filtering for the background
         );
 
-        let ni: NativeImage = NativeImage::read(
-            &data[offset..],
-            self.format,
-            self.width as u32,
-            self.height as u32,
-        )
-        .unwrap();
-
-        let mut decoded_data: Vec<u8> = vec![];
-        let _ = ni.decode(&mut decoded_data, None);
-
-        let img_data = pad_to_length(decoded_data, siz);
-        let img = data_to_color_image(self.width, self.height, img_data.as_slice());
+        let (width, height, img_data) = self.render(data, offset);
+        let img = if self.highlights.is_empty() && self.onion.is_none() {
+            data_to_color_image(width, height, img_data.as_slice())
+        } else {
+            let mut shown = img_data.clone();
+            if let Some(onion) = &self.onion {
+                self.onion_differing = blend(
+                    &mut shown,
+                    width,
+                    &onion.pixels.rgba,
+                    onion.pixels.width,
+                    onion.mode,
+                    onion.opacity,
+                );
+            }
+            self.tint_highlights(offset, width, &mut shown);
+            data_to_color_image(width, height, shown.as_slice())
+        };
 
         // Use NEAREST filtering for crisp pixels
         let tex_options = TextureOptions {
@@ -75,44 +433,281 @@ impl TexView {
         };
         self.tex.set(img, tex_options);
 
-        // Apply zoom to the texture size
-        let zoomed_size = egui::vec2(
-            self.width as f32 * self.zoom,
-            self.height as f32 * self.zoom,
+        RenderCache {
+            key: RenderKey::new(self, generation, offset),
+            width,
+            height,
+            rgba: img_data,
+        }
+    }
+
+    /// Blends the pixels stored in `highlights` towards magenta.
+    ///
+    /// # Arguments
+    /// * `offset` - The offset of the first pixel of the texture.
+    /// * `width` - The width of the rendered image in pixels.
+    /// * `rgba` - The rendered image.
+    fn tint_highlights(&self, offset: usize, width: usize, rgba: &mut [u8]) {
+        if self.highlights.is_empty() {
+            return;
+        }
+        let bits = (bpp_from_image_type(self.format) * 8.0) as usize;
+        let stride = self.stride.unwrap_or(self.width);
+        for (i, pixel) in rgba.chunks_exact_mut(4).enumerate() {
+            let (x, y) = self.texel_at(i % width, i / width);
+            let byte = offset + (y * stride + x) * bits / 8;
+            let index = self.highlights.partition_point(|r| r.end <= byte);
+            if self
+                .highlights
+                .get(index)
+                .is_some_and(|r| r.contains(&byte))
+            {
+                pixel[0] = ((pixel[0] as u16 + 255) / 2) as u8;
+                pixel[1] /= 2;
+                pixel[2] = ((pixel[2] as u16 + 255) / 2) as u8;
+                pixel[3] = 0xFF;
+            }
+        }
+    }
+
+    /// Paints the uploaded texture at `rect`, tracks the hovered pixel and
+    /// draws the overlays.
+    fn paint(
+        &mut self,
+        painter: &egui::Painter,
+        rect: egui::Rect,
+        width: usize,
+        height: usize,
+        img_data: &[u8],
+        ctx: &egui::Context,
+    ) {
+        // Draw the texture scaled to our zoomed size
+        painter.image(
+            self.tex.id(),
+            rect,
+            egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+            Color32::WHITE,
         );
 
-        // Create a group to contain the image
-        egui::Frame::none().fill(self.bg_color).show(ui, |ui| {
-            // Use a fixed size area that matches our zoomed dimensions
-            let (res, painter) = ui.allocate_painter(zoomed_size, Sense::hover());
-
-            // Draw the texture scaled to our zoomed size
-            painter.image(
-                self.tex.id(),
-                res.rect,
-                egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
-                Color32::WHITE,
+        // Outline the original tile in the middle of the tiled preview
+        if self.tiled {
+            let origin = tile_origin(self.tile_repeats);
+            let min = rect.min
+                + egui::vec2(
+                    (origin * self.width) as f32 * self.zoom,
+                    (origin * self.height) as f32 * self.zoom,
+                );
+            let size = egui::vec2(
+                self.width as f32 * self.zoom,
+                self.height as f32 * self.zoom,
+            );
+            painter.rect_stroke(
+                egui::Rect::from_min_size(min, size),
+                0.0,
+                egui::Stroke::new(1.0, Color32::YELLOW),
             );
+        }
+
+        // Handle hover detection
+        self.hovered_pixel = None;
+        if let Some(cursor_pos) = ctx.input(|i| i.pointer.hover_pos()) {
+            if rect.contains(cursor_pos) && painter.clip_rect().contains(cursor_pos) {
+                let relative_pos = cursor_pos - rect.min;
+                // Adjust pixel calculation based on zoom
+                let pixel_x = ((relative_pos.x / rect.width()) * width as f32) as usize;
+                let pixel_y = ((relative_pos.y / rect.height()) * height as f32) as usize;
+                let index = (pixel_y * width + pixel_x) * 4;
+                self.hovered_pixel = Some((pixel_x.min(width - 1), pixel_y.min(height - 1)));
 
-            // Handle hover detection
-            if let Some(cursor_pos) = ctx.input(|i| i.pointer.hover_pos()) {
-                if res.rect.contains(cursor_pos) {
-                    let relative_pos = cursor_pos - res.rect.min;
-                    // Adjust pixel calculation based on zoom
-                    let pixel_x = ((relative_pos.x / zoomed_size.x) * self.width as f32) as usize;
-                    let pixel_y = ((relative_pos.y / zoomed_size.y) * self.height as f32) as usize;
-                    let index = (pixel_y * self.width + pixel_x) * 4;
-
-                    if index + 3 < img_data.len() {
-                        let r = img_data[index];
-                        let g = img_data[index + 1];
-                        let b = img_data[index + 2];
-                        let a = img_data[index + 3];
-                        self.hover_color = Some(Color32::from_rgba_premultiplied(r, g, b, a));
-                    }
+                if index + 3 < img_data.len() {
+                    let r = img_data[index];
+                    let g = img_data[index + 1];
+                    let b = img_data[index + 2];
+                    let a = img_data[index + 3];
+                    self.hover_color = Some(Color32::from_rgba_premultiplied(r, g, b, a));
                 }
             }
-        });
+        }
+
+        self.paint_overlays(painter, rect, width, height);
+    }
+
+    /// Draws the enabled overlays over an image of `width` x `height` pixels
+    /// drawn at `rect`. Only lines inside the painter's clip rect are added.
+    fn paint_overlays(
+        &self,
+        painter: &egui::Painter,
+        rect: egui::Rect,
+        width: usize,
+        height: usize,
+    ) {
+        let scale = self.zoom;
+        let clip = painter.clip_rect().intersect(rect);
+        if !clip.is_positive() {
+            return;
+        }
+
+        // The range of pixel boundaries that are on screen
+        let visible = |min: f32, max: f32, origin: f32, size: usize| {
+            let first = ((min - origin) / scale).floor().max(0.0) as usize;
+            let last = (((max - origin) / scale).ceil().max(0.0) as usize).min(size);
+            first..=last
+        };
+        let columns = visible(clip.min.x, clip.max.x, rect.min.x, width);
+        let rows = visible(clip.min.y, clip.max.y, rect.min.y, height);
+
+        let vertical = |x: usize, stroke: egui::Stroke| {
+            let x = rect.min.x + x as f32 * scale;
+            painter.vline(x, clip.y_range(), stroke);
+        };
+        let horizontal = |y: usize, stroke: egui::Stroke| {
+            let y = rect.min.y + y as f32 * scale;
+            painter.hline(clip.x_range(), y, stroke);
+        };
+
+        if self.overlays.pixel_grid && scale >= MIN_GRID_ZOOM {
+            let stroke = egui::Stroke::new(1.0, Color32::from_black_alpha(96));
+            columns.clone().for_each(|x| vertical(x, stroke));
+            rows.clone().for_each(|y| horizontal(y, stroke));
+        }
+
+        let block = self.overlays.block_size;
+        if block > 0 {
+            let stroke = egui::Stroke::new(1.0, Color32::from_rgba_unmultiplied(0, 160, 255, 160));
+            columns
+                .clone()
+                .filter(|x| x % block == 0)
+                .for_each(|x| vertical(x, stroke));
+            rows.clone()
+                .filter(|y| y % block == 0)
+                .for_each(|y| horizontal(y, stroke));
+        }
+
+        if self.overlays.tmem_words {
+            let pixels_per_word = (8.0 / bpp_from_image_type(self.format)) as usize;
+            let stroke = egui::Stroke::new(1.0, Color32::from_rgba_unmultiplied(255, 64, 64, 200));
+            columns
+                .filter(|x| x % pixels_per_word == 0)
+                .for_each(|x| vertical(x, stroke));
+        }
+
+        if let (true, Some((x, y))) = (self.overlays.crosshair, self.hovered_pixel) {
+            let stroke = egui::Stroke::new(1.0, Color32::from_rgba_unmultiplied(255, 255, 0, 160));
+            let center = rect.min + egui::vec2(x as f32 + 0.5, y as f32 + 0.5) * scale;
+            painter.vline(center.x, clip.y_range(), stroke);
+            painter.hline(clip.x_range(), center.y, stroke);
+            painter.rect_stroke(
+                egui::Rect::from_center_size(center, egui::Vec2::splat(scale)),
+                0.0,
+                egui::Stroke::new(1.0, Color32::YELLOW),
+            );
+        }
+    }
+
+    /// Maps a pixel of the drawn image back to the texel it was sampled
+    /// from, undoing the tiled preview.
+    pub fn texel_at(&self, x: usize, y: usize) -> (usize, usize) {
+        if !self.tiled {
+            return (x, y);
+        }
+        let origin_s = (tile_origin(self.tile_repeats) * self.width) as i32;
+        let origin_t = (tile_origin(self.tile_repeats) * self.height) as i32;
+        let tile = &self.tile;
+        (
+            wrap_coord(
+                x as i32 - origin_s,
+                self.width,
+                tile.cms,
+                tile.masks,
+                tile.shifts,
+            ),
+            wrap_coord(
+                y as i32 - origin_t,
+                self.height,
+                tile.cmt,
+                tile.maskt,
+                tile.shiftt,
+            ),
+        )
+    }
+
+    /// Reads the encoded value of the hovered pixel from the texture at
+    /// `offset`.
+    pub fn hovered_raw_pixel(&self, data: &[u8], offset: usize) -> Option<RawPixel> {
+        let (x, y) = self.hovered_pixel?;
+        let (x, y) = self.texel_at(x, y);
+        raw_pixel(
+            data,
+            offset,
+            self.format,
+            self.stride.unwrap_or(self.width),
+            self.swap,
+            x,
+            y,
+        )
+    }
+
+    /// Renders the texture at `offset` for use as an onion skin, reusing the
+    /// last result while nothing it depends on changes.
+    ///
+    /// # Arguments
+    /// * `data` - The file to read, not necessarily the one being drawn.
+    /// * `generation` - The `BinFile::generation` of `data`.
+    /// * `offset` - The offset of the first pixel.
+    pub fn render_overlay(&mut self, data: &[u8], generation: u64, offset: usize) -> OverlayPixels {
+        match &self.overlay_cache {
+            Some(cache) if cache.key.matches(self, generation, offset) => cache.pixels.clone(),
+            _ => {
+                let (width, _, rgba) = self.render(data, offset);
+                let pixels = OverlayPixels::new(width, rgba);
+                self.overlay_cache = Some(OverlayCache {
+                    key: DecodeKey::new(self, generation, offset),
+                    pixels: pixels.clone(),
+                });
+                pixels
+            }
+        }
+    }
+
+    /// Decodes the texture at `offset` and applies the combiner and tiling,
+    /// producing exactly what `draw` shows.
+    ///
+    /// Returns the width, height and RGBA8 pixels of the result.
+    pub fn render(&self, data: &[u8], offset: usize) -> (usize, usize, Vec<u8>) {
+        let swapped;
+        let data = match self.swap {
+            ByteSwap::None => data.get(offset..).unwrap_or_default(),
+            swap => {
+                let row = self.stride.unwrap_or(self.width).max(self.width);
+                swapped = swap.read(data, offset, image_byte_size(self.format, row, self.height));
+                &swapped[..]
+            }
+        };
+        let gathered;
+        let data = match self.stride {
+            Some(stride) if stride != self.width => {
+                gathered = gather_rows(data, self.format, self.width, self.height, stride);
+                &gathered[..]
+            }
+            _ => data,
+        };
+
+        let mut rgba = decode_texture(
+            data,
+            self.format,
+            self.width,
+            self.height,
+            self.palette.as_deref(),
+        );
+        self.combiner.apply(&mut rgba);
+
+        if !self.tiled {
+            return (self.width, self.height, rgba);
+        }
+        let width = self.width * self.tile_repeats;
+        let height = self.height * self.tile_repeats;
+        let tiled = tile_texture(&rgba, self.width, self.height, &self.tile, width, height);
+        (width, height, tiled)
     }
 
     pub fn update_dimensions(&mut self, format: ImageType, data_size: usize) {
@@ -135,6 +730,267 @@ impl TexView {
     }
 }
 
+/// Decodes N64 texel data to RGBA8 through pigment64.
+///
+/// The result is always `width * height * 4` bytes long; pixels past the end
+/// of `data` are left transparent. CI formats without a usable palette are
+/// shown with a grayscale ramp so the indices are still visible.
+///
+/// # Arguments
+/// * `data` - The texel data, starting at the first pixel.
+/// * `format` - The N64 image format.
+/// * `width` - The width of the image in pixels.
+/// * `height` - The height of the image in pixels.
+/// * `tlut` - The raw RGBA16 palette for CI formats.
+pub fn decode_texture(
+    data: &[u8],
+    format: ImageType,
+    width: usize,
+    height: usize,
+    tlut: Option<&[u8]>,
+) -> Vec<u8> {
+    let siz = width * height * 4;
+
+    let palette = match format {
+        ImageType::Ci4 | ImageType::Ci8 => Some(
+            tlut.and_then(|tlut| parse_tlut(tlut, format.get_size(), TextureLUT::Rgba16).ok())
+                .unwrap_or_else(|| grayscale_palette(format)),
+        ),
+        _ => None,
+    };
+
+    let mut decoded_data: Vec<u8> = vec![];
+    let data = &data[..image_byte_size(format, width, height).min(data.len())];
+    if let Ok(ni) = NativeImage::read(data, format, width as u32, height as u32) {
+        let _ = ni.decode(&mut decoded_data, palette.as_deref());
+    }
+
+    pad_to_length(decoded_data, siz)
+}
+
+/// Copies `height` rows of `width` pixels out of an image whose rows are
+/// `stride` pixels apart, producing tightly packed rows.
+///
+/// # Arguments
+/// * `data` - The image data, starting at the first pixel.
+/// * `format` - The N64 image format.
+/// * `width` - The visible width of the image in pixels.
+/// * `height` - The height of the image in pixels.
+/// * `stride` - The distance between the starts of two rows, in pixels.
+pub fn gather_rows(
+    data: &[u8],
+    format: ImageType,
+    width: usize,
+    height: usize,
+    stride: usize,
+) -> Vec<u8> {
+    let row_bytes = image_byte_size(format, width, 1);
+    let stride_bytes = image_byte_size(format, stride, 1);
+
+    let mut out = Vec::with_capacity(row_bytes * height);
+    for row in 0..height {
+        let start = row * stride_bytes;
+        let Some(bytes) = data.get(start..) else {
+            break;
+        };
+        out.extend_from_slice(&bytes[..row_bytes.min(bytes.len())]);
+    }
+    out
+}
+
+/// A single pixel as it is encoded in the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawPixel {
+    /// The texel coordinates of the pixel.
+    pub x: usize,
+    pub y: usize,
+    /// Offset in the data of the first byte holding the pixel, after undoing
+    /// any byte swap.
+    pub offset: usize,
+    /// The lowest bit the pixel occupies within its byte; 0 for formats of
+    /// a byte or more.
+    pub bit: u8,
+    /// The number of bits per pixel.
+    pub bits: u8,
+    /// The encoded value, e.g. the RGBA5551 word or the CI index.
+    pub value: u32,
+}
+
+/// Reads the encoded value of one pixel.
+///
+/// Returns `None` if the pixel lies past the end of `data`.
+///
+/// # Arguments
+/// * `data` - The whole file.
+/// * `offset` - The offset of the first pixel of the image.
+/// * `format` - The N64 image format.
+/// * `stride` - The distance between the starts of two rows, in pixels.
+/// * `swap` - The byte order transform applied before decoding.
+/// * `x` - The column of the pixel.
+/// * `y` - The row of the pixel.
+pub fn raw_pixel(
+    data: &[u8],
+    offset: usize,
+    format: ImageType,
+    stride: usize,
+    swap: ByteSwap,
+    x: usize,
+    y: usize,
+) -> Option<RawPixel> {
+    let bits = (bpp_from_image_type(format) * 8.0) as usize;
+    let bit_index = (y * stride + x) * bits;
+    let start = offset + bit_index / 8;
+    let len = bits.div_ceil(8);
+
+    let bytes = swap.read(data, start, len);
+    if bytes.len() < len {
+        return None;
+    }
+    let value = if bits < 8 {
+        let shift = 8 - bits - bit_index % 8;
+        (bytes[0] as u32 >> shift) & ((1 << bits) - 1)
+    } else {
+        bytes.iter().fold(0, |acc, &b| (acc << 8) | b as u32)
+    };
+
+    // Where the first byte really is before swapping
+    let word = swap.word_size();
+    let physical = start - start % word + (word - 1 - start % word);
+
+    Some(RawPixel {
+        x,
+        y,
+        offset: physical,
+        bit: if bits < 8 {
+            (8 - bits - bit_index % 8) as u8
+        } else {
+            0
+        },
+        bits: bits as u8,
+        value,
+    })
+}
+
+/// Returns which repeat of a tiled preview holds the original tile.
+fn tile_origin(repeats: usize) -> usize {
+    repeats.saturating_sub(1) / 2
+}
+
+/// Maps a texel coordinate to one inside the texture the way the RDP does
+/// for a tile descriptor axis: shift first, then clamp, then mask and mirror.
+///
+/// A mask of 0 disables wrapping, which behaves like a clamp.
+///
+/// # Arguments
+/// * `coord` - The coordinate relative to the start of the texture.
+/// * `size` - The texture size along this axis.
+/// * `cm` - The `cms`/`cmt` flags.
+/// * `mask` - The `masks`/`maskt` value; wrapping happens every `1 << mask` texels.
+/// * `shift` - The `shifts`/`shiftt` value.
+pub fn wrap_coord(coord: i32, size: usize, cm: u8, mask: u8, shift: u8) -> usize {
+    let size = size.max(1) as i32;
+    let mut coord = match shift {
+        0 => coord,
+        1..=10 => coord >> shift,
+        _ => coord << (16 - shift.min(15)),
+    };
+
+    if cm & TileDescriptor::CLAMP != 0 || mask == 0 {
+        coord = coord.clamp(0, size - 1);
+    }
+
+    if mask != 0 {
+        let period = 1i32 << mask.min(10);
+        let wrapped = coord.rem_euclid(period);
+        let mirrored = cm & TileDescriptor::MIRROR != 0 && coord.div_euclid(period) % 2 != 0;
+        coord = if mirrored {
+            period - 1 - wrapped
+        } else {
+            wrapped
+        };
+    }
+
+    coord.clamp(0, size - 1) as usize
+}
+
+/// Repeats an RGBA8 texture over a larger area following the RDP tile
+/// semantics of `tile`. The original texture is placed in the middle of the
+/// output, so wrapping is visible on every side.
+///
+/// # Arguments
+/// * `rgba` - The decoded texture.
+/// * `width` - The width of the texture in pixels.
+/// * `height` - The height of the texture in pixels.
+/// * `tile` - The wrap, mirror, clamp, mask and shift settings.
+/// * `out_width` - The width of the output in pixels.
+/// * `out_height` - The height of the output in pixels.
+pub fn tile_texture(
+    rgba: &[u8],
+    width: usize,
+    height: usize,
+    tile: &TileDescriptor,
+    out_width: usize,
+    out_height: usize,
+) -> Vec<u8> {
+    let origin_s = (tile_origin(out_width / width.max(1)) * width) as i32;
+    let origin_t = (tile_origin(out_height / height.max(1)) * height) as i32;
+
+    let mut out = Vec::with_capacity(out_width * out_height * 4);
+    for y in 0..out_height {
+        let t = wrap_coord(
+            y as i32 - origin_t,
+            height,
+            tile.cmt,
+            tile.maskt,
+            tile.shiftt,
+        );
+        for x in 0..out_width {
+            let s = wrap_coord(
+                x as i32 - origin_s,
+                width,
+                tile.cms,
+                tile.masks,
+                tile.shifts,
+            );
+            let index = (t * width + s) * 4;
+            out.extend_from_slice(rgba.get(index..index + 4).unwrap_or(&[0; 4]));
+        }
+    }
+    out
+}
+
+/// Returns the number of bytes a pixel of `image_type` occupies.
+pub fn bpp_from_image_type(image_type: ImageType) -> f32 {
+    match image_type {
+        ImageType::I1 => 0.125,
+        ImageType::I4 => 0.5,
+        ImageType::I8 => 1.0,
+        ImageType::Ia4 => 0.5,
+        ImageType::Ia8 => 1.0,
+        ImageType::Ia16 => 2.0,
+        ImageType::Ci4 => 0.5,
+        ImageType::Ci8 => 1.0,
+        ImageType::Rgba16 => 2.0,
+        ImageType::Rgba32 => 4.0,
+    }
+}
+
+/// Returns the number of bytes a `width` x `height` image of `format` occupies.
+pub fn image_byte_size(format: ImageType, width: usize, height: usize) -> usize {
+    (width as f32 * height as f32 * bpp_from_image_type(format)).ceil() as usize
+}
+
+/// Builds an RGBA8 palette that maps each CI index to a gray level.
+fn grayscale_palette(format: ImageType) -> Vec<u8> {
+    let entries = format.get_size().get_tlut_size();
+    (0..entries)
+        .flat_map(|i| {
+            let level = (i * 255 / (entries - 1)) as u8;
+            [level, level, level, 0xFF]
+        })
+        .collect()
+}
+
 // Helper function to convert raw image data to egui ColorImage
 fn data_to_color_image(width: usize, height: usize, data: &[u8]) -> ColorImage {
     assert!(data.len() >= width * height * 4);
@@ -150,7 +1006,7 @@ pub fn pad_to_length(data: Vec<u8>, length: usize) -> Vec<u8> {
     match data.len() < length {
         true => {
             let mut ret: Vec<u8> = data.clone();
-            ret.extend(iter::repeat(0).take(length - data.len()));
+            ret.extend(iter::repeat_n(0, length - data.len()));
 
             ret
         }
@@ -0,0 +1,224 @@
+use std::ops::Range;
+
+use eframe::egui::{self, Color32, Sense, Stroke};
+use egui_extras::{Column, TableBuilder};
+
+use crate::{display_list::Vtx, model_view::orbit};
+
+/// Interprets the bytes at the file position as an array of `Vtx` structs.
+pub struct VtxView {
+    /// The number of vertices to read.
+    pub count: usize,
+    /// Whether to show the last four bytes as a normal instead of a color.
+    pub normals: bool,
+    /// Preview rotation around the vertical axis, in radians.
+    pub yaw: f32,
+    /// Preview rotation around the horizontal axis, in radians.
+    pub pitch: f32,
+    /// The row that is currently hovered.
+    hovered: Option<usize>,
+    /// Where each row was drawn last frame, so the hovered row can be found
+    /// before the table is drawn again.
+    row_rects: Vec<(usize, egui::Rect)>,
+}
+
+impl Default for VtxView {
+    fn default() -> Self {
+        Self {
+            count: 16,
+            normals: false,
+            yaw: 0.5,
+            pitch: 0.3,
+            hovered: None,
+            row_rects: vec![],
+        }
+    }
+}
+
+/// Reads up to `count` vertices starting at `offset`.
+pub fn read_vertices(data: &[u8], offset: usize, count: usize) -> Vec<Vtx> {
+    data.get(offset..)
+        .unwrap_or_default()
+        .chunks_exact(Vtx::SIZE)
+        .take(count)
+        .filter_map(Vtx::read)
+        .collect()
+}
+
+impl VtxView {
+    /// Finds the vertex row under `pointer` using last frame's layout.
+    ///
+    /// This is known before anything is drawn, so panels drawn before the
+    /// table can show the hovered vertex in the same frame.
+    ///
+    /// Returns the byte range of the hovered vertex, if any.
+    pub fn hovered_range(
+        &mut self,
+        pointer: Option<egui::Pos2>,
+        offset: usize,
+    ) -> Option<Range<usize>> {
+        self.hovered = pointer.and_then(|pos| {
+            self.row_rects
+                .iter()
+                .find(|(_, rect)| rect.contains(pos))
+                .map(|&(i, _)| i)
+        });
+        self.hovered.map(|i| {
+            let start = offset + i * Vtx::SIZE;
+            start..start + Vtx::SIZE
+        })
+    }
+
+    /// Draws the controls, the vertex table and the point cloud preview.
+    pub fn draw(&mut self, ui: &mut egui::Ui, data: &[u8], offset: usize) {
+        let vertices = read_vertices(data, offset, self.count);
+
+        ui.horizontal(|ui| {
+            ui.label("Count:");
+            ui.add(egui::DragValue::new(&mut self.count).range(1..=1024));
+            ui.checkbox(&mut self.normals, "Normals");
+        });
+
+        ui.horizontal_top(|ui| {
+            let preview_size = egui::vec2(200.0, 200.0);
+            let table_width = (ui.available_width() - preview_size.x - 8.0).max(200.0);
+
+            ui.allocate_ui(egui::vec2(table_width, ui.available_height()), |ui| {
+                self.row_rects = self.draw_table(ui, &vertices, offset);
+            });
+            self.draw_preview(ui, &vertices, preview_size);
+        });
+    }
+
+    /// Draws the vertex table.
+    ///
+    /// Returns where each visible row was drawn.
+    fn draw_table(
+        &self,
+        ui: &mut egui::Ui,
+        vertices: &[Vtx],
+        offset: usize,
+    ) -> Vec<(usize, egui::Rect)> {
+        let mut row_rects = vec![];
+        let headers = if self.normals {
+            ["NX", "NY", "NZ", "A"]
+        } else {
+            ["R", "G", "B", "A"]
+        };
+
+        TableBuilder::new(ui)
+            .striped(true)
+            .sense(Sense::hover())
+            .columns(Column::auto().at_least(36.0), 13)
+            .header(18.0, |mut header| {
+                for title in ["#", "Offset", "X", "Y", "Z", "Flag", "S", "T"]
+                    .iter()
+                    .chain(headers.iter())
+                {
+                    header.col(|ui| {
+                        ui.strong(*title);
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(18.0, vertices.len(), |mut row| {
+                    let i = row.index();
+                    let vtx = &vertices[i];
+                    row.set_hovered(self.hovered == Some(i));
+
+                    row.col(|ui| {
+                        ui.label(i.to_string());
+                    });
+                    row.col(|ui| {
+                        ui.monospace(format!("{:08X}", offset + i * Vtx::SIZE));
+                    });
+                    for p in vtx.pos {
+                        row.col(|ui| {
+                            ui.monospace(p.to_string());
+                        });
+                    }
+                    row.col(|ui| {
+                        ui.monospace(format!("{:04X}", vtx.flag));
+                    });
+                    for tc in vtx.texcoords() {
+                        row.col(|ui| {
+                            ui.monospace(format!("{:.2}", tc));
+                        });
+                    }
+                    if self.normals {
+                        for n in vtx.normal() {
+                            row.col(|ui| {
+                                ui.monospace(format!("{:.2}", n));
+                            });
+                        }
+                        row.col(|ui| {
+                            ui.monospace(vtx.cn[3].to_string());
+                        });
+                    } else {
+                        for c in vtx.cn {
+                            row.col(|ui| {
+                                ui.monospace(c.to_string());
+                            });
+                        }
+                    }
+
+                    row_rects.push((i, row.response().rect));
+                });
+            });
+
+        row_rects
+    }
+
+    /// Draws the vertices as a point cloud that can be rotated by dragging.
+    fn draw_preview(&mut self, ui: &mut egui::Ui, vertices: &[Vtx], size: egui::Vec2) {
+        let (rect, response) = ui.allocate_exact_size(size, Sense::drag());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, Color32::from_gray(32));
+
+        if response.dragged() {
+            let delta = response.drag_delta();
+            self.yaw += delta.x * 0.01;
+            self.pitch = (self.pitch + delta.y * 0.01).clamp(-1.5, 1.5);
+        }
+
+        let Some(first) = vertices.first() else {
+            return;
+        };
+        let (min, max) = vertices
+            .iter()
+            .fold((first.pos, first.pos), |(min, max), v| {
+                (
+                    std::array::from_fn(|i| min[i].min(v.pos[i])),
+                    std::array::from_fn(|i| max[i].max(v.pos[i])),
+                )
+            });
+        let center: [f32; 3] = std::array::from_fn(|i| (min[i] as f32 + max[i] as f32) / 2.0);
+        let radius = (0..3)
+            .map(|i| (max[i] as f32 - min[i] as f32) / 2.0)
+            .map(|d| d * d)
+            .sum::<f32>()
+            .sqrt()
+            .max(1.0);
+        let scale = rect.width().min(rect.height()) / 2.0 * 0.9 / radius;
+
+        for (i, vtx) in vertices.iter().enumerate() {
+            let [x, y, _] = orbit(
+                std::array::from_fn(|j| vtx.pos[j] as f32 - center[j]),
+                self.yaw,
+                self.pitch,
+            );
+            let pos = rect.center() + egui::vec2(x * scale, -y * scale);
+            let color = if self.normals {
+                Color32::LIGHT_GRAY
+            } else {
+                Color32::from_rgb(vtx.cn[0], vtx.cn[1], vtx.cn[2])
+            };
+
+            if self.hovered == Some(i) {
+                painter.circle(pos, 5.0, color, Stroke::new(2.0, Color32::YELLOW));
+            } else {
+                painter.circle_filled(pos, 2.5, color);
+            }
+        }
+    }
+}
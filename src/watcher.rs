@@ -0,0 +1,94 @@
+use std::{
+    path::Path,
+    time::{Duration, Instant, SystemTime},
+};
+
+/// How often the watched file is checked.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The modification time and size of a file, which change when it is
+/// rewritten.
+pub type FileStamp = (SystemTime, u64);
+
+/// Reads the stamp of the file at `path`, if it exists.
+pub fn file_stamp(path: &Path) -> Option<FileStamp> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// Notices when the open file is rewritten on disk.
+///
+/// The file is polled rather than watched through OS notifications, so it
+/// also works when build tools replace the file instead of writing to it.
+/// A change is only reported once the stamp has stayed the same for one
+/// poll, so a file that is still being written is not read half-done.
+#[derive(Debug, Default)]
+pub struct FileWatcher {
+    /// Whether changes are ignored.
+    pub paused: bool,
+    /// When the file was last reloaded because of a change.
+    pub last_reload: Option<Instant>,
+    /// The stamp of the file as it was loaded.
+    loaded: Option<FileStamp>,
+    /// A new stamp seen on the last poll, waiting to settle.
+    pending: Option<FileStamp>,
+    last_poll: Option<Instant>,
+}
+
+impl FileWatcher {
+    /// Starts watching `path` as it is now.
+    pub fn watch(&mut self, path: &Path) {
+        self.loaded = file_stamp(path);
+        self.pending = None;
+    }
+
+    /// Stops watching, for files that must not be reloaded.
+    pub fn unwatch(&mut self) {
+        self.loaded = None;
+        self.pending = None;
+    }
+
+    /// Whether a file is being watched for changes, which needs regular
+    /// polls.
+    pub fn is_active(&self) -> bool {
+        !self.paused && self.loaded.is_some()
+    }
+
+    /// Checks the file if a poll is due.
+    ///
+    /// Returns `true` when the file changed and should be reloaded.
+    pub fn poll(&mut self, path: &Path) -> bool {
+        let now = Instant::now();
+        if self.paused
+            || self.loaded.is_none()
+            || self
+                .last_poll
+                .is_some_and(|last| now.duration_since(last) < POLL_INTERVAL)
+        {
+            return false;
+        }
+        self.last_poll = Some(now);
+        self.check(file_stamp(path))
+    }
+
+    /// Compares a freshly read stamp with the loaded one.
+    ///
+    /// Returns `true` when a new stamp was seen twice in a row.
+    pub fn check(&mut self, stamp: Option<FileStamp>) -> bool {
+        let Some(stamp) = stamp else {
+            // Mid-replace; wait for the file to come back
+            return false;
+        };
+        if Some(stamp) == self.loaded {
+            self.pending = None;
+            return false;
+        }
+        if self.pending != Some(stamp) {
+            self.pending = Some(stamp);
+            return false;
+        }
+        self.loaded = Some(stamp);
+        self.pending = None;
+        true
+    }
+}
@@ -17,7 +17,7 @@ mod tests {
         let expected_path = file_path.to_path_buf();
         let expected_data = b"Hello there!";
 
-        let bin_file = BinFile::from_path(&file_path).unwrap();
+        let bin_file = BinFile::from_path(file_path).unwrap();
 
         // Check both the path and data fields in one test
         assert_eq!(bin_file.path, expected_path);
@@ -52,4 +52,39 @@ mod tests {
         // Check that an error is returned, instead of panicking
         assert!(result.data.is_empty(), "Expected file to be empty");
     }
+
+    /// Tests mapping between file offsets and virtual addresses.
+    #[test]
+    fn test_base_address() {
+        let mut bin_file = BinFile::from_path(Path::new("tests/test_files/hello.txt")).unwrap();
+        assert_eq!(bin_file.offset_of(0x8000_0004), None);
+
+        bin_file.base_address = 0x8000_0000;
+        assert_eq!(bin_file.address_of(4), 0x8000_0004);
+        assert_eq!(bin_file.offset_of(0x8000_0004), Some(4));
+        assert_eq!(bin_file.offset_of(0x8000_0100), None);
+        assert_eq!(bin_file.offset_of(0x0000_0004), None);
+    }
+
+    /// Tests that every load of a file gets a new generation.
+    #[test]
+    fn test_generation() {
+        let path = Path::new("tests/test_files/hello.txt");
+        let first = BinFile::from_path(path).unwrap();
+        let second = BinFile::from_path(path).unwrap();
+        assert_ne!(first.generation, 0);
+        assert_ne!(first.generation, second.generation);
+    }
+
+    /// Tests that a mapped file reads the same as one read into memory.
+    #[test]
+    fn test_map() {
+        let path = Path::new("tests/test_files/hello.txt");
+        let mapped = BinFile::map(path).unwrap();
+        assert!(mapped.is_mapped());
+        assert!(!BinFile::from_path(path).unwrap().is_mapped());
+        assert!(!BinFile::read(path).unwrap().is_mapped());
+        assert_eq!(mapped.data, b"Hello there!");
+        assert_eq!(&mapped.data[6..], b"there!");
+    }
 }
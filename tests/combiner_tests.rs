@@ -0,0 +1,45 @@
+use motex::combiner::{CombineMode, Combiner};
+
+#[cfg(test)]
+mod combiner_tests {
+    use super::*;
+
+    fn combine(mode: CombineMode, texel: [u8; 4]) -> [u8; 4] {
+        let combiner = Combiner {
+            mode,
+            prim: [0xFF, 0x80, 0x00, 0xFF],
+            env: [0x00, 0x00, 0xFF, 0x80],
+        };
+        let mut rgba = texel;
+        combiner.apply(&mut rgba);
+        rgba
+    }
+
+    #[test]
+    fn test_texel0_is_unchanged() {
+        assert_eq!(combine(CombineMode::Texel0, [1, 2, 3, 4]), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_modulate_prim() {
+        let white = [0xFF; 4];
+        assert_eq!(
+            combine(CombineMode::ModulatePrim, white),
+            [0xFF, 0x80, 0x00, 0xFF]
+        );
+        assert_eq!(combine(CombineMode::ModulatePrim, [0; 4]), [0; 4]);
+    }
+
+    #[test]
+    fn test_lerp_env_prim() {
+        // Black texels take the environment color, white ones the primitive
+        assert_eq!(
+            combine(CombineMode::LerpEnvPrim, [0, 0, 0, 0xFF]),
+            [0x00, 0x00, 0xFF, 0xFF]
+        );
+        assert_eq!(
+            combine(CombineMode::LerpEnvPrim, [0xFF; 4]),
+            [0xFF, 0x80, 0x00, 0xFF]
+        );
+    }
+}
@@ -0,0 +1,8 @@
+/// Creates an empty directory for one test, named after the test and the
+/// process so that overlapping runs do not share it.
+pub fn test_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("motex_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
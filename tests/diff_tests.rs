@@ -0,0 +1,80 @@
+use motex::diff::{
+    diff_ranges, diff_ranges_with_progress, find_alignment, find_alignment_with_progress,
+};
+
+#[cfg(test)]
+mod diff_tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_ranges() {
+        let a = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9];
+        let b = [0, 9, 9, 3, 4, 5, 6, 9, 8];
+        assert_eq!(diff_ranges(&a, &b, 0, 0), [1..3, 7..8, 9..10]);
+        assert_eq!(diff_ranges(&a, &b, 0, 4), [1..3, 7..10]);
+        assert!(diff_ranges(&a, &a, 0, 16).is_empty());
+    }
+
+    #[test]
+    fn test_shifted_diff() {
+        let a: Vec<u8> = (0..64).collect();
+        let mut b = vec![0xFF; 8];
+        b.extend(&a);
+        assert!(diff_ranges(&a, &b, 8, 0).is_empty());
+        let ranges = diff_ranges(&a, &b[16..], -8, 0);
+        assert_eq!(ranges.len(), 1);
+        assert_eq!(ranges[0], 0..8);
+    }
+
+    #[test]
+    fn test_diff_progress() {
+        let a = vec![1u8; 100];
+        let b = vec![0u8; 100];
+        let mut calls = 0;
+        let ranges = diff_ranges_with_progress(&a, &b, 0, 0, 10, |_| {
+            calls += 1;
+            true
+        });
+        assert_eq!(ranges.len(), 1);
+        assert_eq!(ranges[0], 0..100);
+        assert_eq!(calls, 10);
+
+        // Cancelled after the first chunk
+        let mut calls = 0;
+        let ranges = diff_ranges_with_progress(&a, &b, 0, 0, 10, |_| {
+            calls += 1;
+            calls == 1
+        });
+        assert_eq!(ranges.len(), 1);
+        assert_eq!(ranges[0], 0..10);
+    }
+
+    #[test]
+    fn test_find_alignment() {
+        let a: Vec<u8> = (0..=255)
+            .cycle()
+            .take(1000)
+            .map(|i: u8| i.wrapping_mul(7))
+            .collect();
+        let mut b = vec![0x55; 12];
+        b.extend(&a);
+        assert_eq!(find_alignment(&a, &b, 32), 12);
+        assert_eq!(find_alignment(&b, &a, 32), -12);
+        assert_eq!(find_alignment(&a, &a, 32), 0);
+    }
+
+    #[test]
+    fn test_find_alignment_stops_when_cancelled() {
+        let a: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        let mut b = vec![0x55; 200];
+        b.extend(&a);
+        assert_eq!(find_alignment(&a, &b, 256), 200);
+        // Stopped before distance 200 was tried
+        let mut calls = 0;
+        let shift = find_alignment_with_progress(&a, &b, 256, |_| {
+            calls += 1;
+            calls < 2
+        });
+        assert_ne!(shift, 200);
+    }
+}
@@ -0,0 +1,257 @@
+use motex::display_list::{Command, Interpreter, Microcode, Vtx};
+use motex::segments::{AddressSpace, SegmentSource, SegmentTable};
+use pigment64::ImageType;
+
+/// Appends a command to a display list buffer.
+fn push(buf: &mut Vec<u8>, w0: u32, w1: u32) {
+    buf.extend_from_slice(&w0.to_be_bytes());
+    buf.extend_from_slice(&w1.to_be_bytes());
+}
+
+/// Appends a vertex to a buffer.
+fn push_vtx(buf: &mut Vec<u8>, pos: [i16; 3], tc: [i16; 2], cn: [u8; 4]) {
+    for p in pos {
+        buf.extend_from_slice(&p.to_be_bytes());
+    }
+    buf.extend_from_slice(&0u16.to_be_bytes());
+    for t in tc {
+        buf.extend_from_slice(&t.to_be_bytes());
+    }
+    buf.extend_from_slice(&cn);
+}
+
+/// Builds a file with three vertices at 0x00, texels at 0x40 and a
+/// display list at 0x100 that draws one textured triangle.
+fn textured_triangle() -> Vec<u8> {
+    let mut data = vec![];
+    push_vtx(&mut data, [0, 0, 0], [0, 0], [0xFF, 0, 0, 0xFF]);
+    push_vtx(&mut data, [100, 0, 0], [32 * 32, 0], [0, 0xFF, 0, 0xFF]);
+    push_vtx(&mut data, [0, 100, 0], [0, 32 * 32], [0, 0, 0xFF, 0xFF]);
+    data.resize(0x100, 0xAA);
+
+    // gsSPTexture(0xFFFF, 0xFFFF, 0, G_TX_RENDERTILE, G_ON)
+    push(&mut data, 0xD700_0002, 0xFFFF_FFFF);
+    // gsDPSetTextureImage(G_IM_FMT_RGBA, G_IM_SIZ_16b, 1, 0x06000040)
+    push(&mut data, 0xFD10_0000, 0x0600_0040);
+    // gsDPSetTile(G_IM_FMT_RGBA, G_IM_SIZ_16b, 0, 0, G_TX_LOADTILE, ...)
+    push(&mut data, 0xF510_0000, 0x0700_0000);
+    push(&mut data, 0xF300_0000, 0x073F_F100);
+    // gsDPSetTile(G_IM_FMT_RGBA, G_IM_SIZ_16b, 8, 0, G_TX_RENDERTILE, ...)
+    push(&mut data, 0xF510_1000, 0x0000_0000);
+    // gsDPSetTileSize(G_TX_RENDERTILE, 0, 0, 31 << 2, 31 << 2)
+    push(&mut data, 0xF200_0000, 0x0007_C07C);
+    // gsSPVertex(0x06000000, 3, 0)
+    push(&mut data, 0x0100_3006, 0x0600_0000);
+    // gsSP1Triangle(0, 1, 2, 0)
+    push(&mut data, 0x0500_0204, 0x0000_0000);
+    push(&mut data, 0xDF00_0000, 0x0000_0000);
+    data
+}
+
+/// Builds a file with three vertices at 0x00, CI4 texels at 0x40, a
+/// palette at 0xC0 and a display list at 0x100 that block-loads the texels
+/// as 16-bit and draws one triangle through a 16x16 CI4 render tile.
+fn ci4_triangle() -> Vec<u8> {
+    let mut data = vec![];
+    push_vtx(&mut data, [0, 0, 0], [0, 0], [0xFF; 4]);
+    push_vtx(&mut data, [100, 0, 0], [0, 0], [0xFF; 4]);
+    push_vtx(&mut data, [0, 100, 0], [0, 0], [0xFF; 4]);
+    data.resize(0xC0, 0x11);
+    // Palette: 16 RGBA16 colors
+    for i in 0..16u16 {
+        data.extend_from_slice(&(i << 1 | 1).to_be_bytes());
+    }
+    data.resize(0x100, 0);
+
+    push(&mut data, 0xD700_0002, 0xFFFF_FFFF);
+    // Palette load into TMEM 256 through tile 7
+    push(&mut data, 0xFD10_0000, 0x0600_00C0);
+    push(&mut data, 0xF500_0100, 0x0700_0000);
+    push(&mut data, 0xF000_0000, 0x0703_C000);
+    // Texels through tile 7, then a 16x16 CI4 render tile
+    push(&mut data, 0xFD50_0000, 0x0600_0040);
+    push(&mut data, 0xF550_0000, 0x0700_0000);
+    push(&mut data, 0xF300_0000, 0x0707_F800);
+    push(&mut data, 0xF540_0000, 0x0000_0000);
+    push(&mut data, 0xF200_0000, 0x0003_C03C);
+    push(&mut data, 0x0100_3006, 0x0600_0000);
+    // 0x150: gsSP1Triangle(0, 1, 2, 0)
+    push(&mut data, 0x0500_0204, 0x0000_0000);
+    push(&mut data, 0xDF00_0000, 0x0000_0000);
+    data
+}
+
+#[cfg(test)]
+mod vtx_tests {
+    use super::*;
+
+    #[test]
+    fn test_vtx_read() {
+        let mut data = vec![];
+        push_vtx(&mut data, [-1, 2, 300], [64, -32], [1, 2, 3, 4]);
+
+        let vtx = Vtx::read(&data).unwrap();
+        assert_eq!(vtx.pos, [-1, 2, 300]);
+        assert_eq!(vtx.texcoords(), [2.0, -1.0]);
+        assert_eq!(vtx.cn, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_vtx_read_short_buffer() {
+        assert!(Vtx::read(&[0; 15]).is_none());
+    }
+}
+
+#[cfg(test)]
+mod decode_tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_f3dex2_vertex() {
+        let command = Microcode::F3dex2.decode(0x0100_3006, 0x0600_0000);
+        assert_eq!(
+            command,
+            Command::Vertex {
+                addr: 0x0600_0000,
+                start: 0,
+                count: 3
+            }
+        );
+    }
+
+    #[test]
+    fn test_decode_f3dex_triangle() {
+        let command = Microcode::F3dex.decode(0xBF00_0000, 0x0000_0204);
+        assert_eq!(command, Command::Triangle([0, 1, 2]));
+    }
+
+    #[test]
+    fn test_decode_branch() {
+        let command = Microcode::F3dex2.decode(0xDE01_0000, 0x0600_0100);
+        assert_eq!(
+            command,
+            Command::DisplayList {
+                addr: 0x0600_0100,
+                branch: true
+            }
+        );
+    }
+}
+
+#[cfg(test)]
+mod interpreter_tests {
+    use super::*;
+
+    #[test]
+    fn test_textured_triangle() {
+        let data = textured_triangle();
+        let mut segments = SegmentTable::default();
+        segments.set(6, 0, SegmentSource::OpenFile);
+        let memory = AddressSpace::new(&segments, &data);
+
+        let mesh = Interpreter::new(&memory, Microcode::F3dex2)
+            .run(0x0600_0100)
+            .unwrap();
+
+        assert!(mesh.warnings.is_empty(), "{:?}", mesh.warnings);
+        assert_eq!(mesh.triangles.len(), 1);
+        assert_eq!(mesh.vertices[1].pos, [100.0, 0.0, 0.0]);
+        assert_eq!(mesh.vertices[1].uv, [1.0, 0.0]);
+        assert_eq!(mesh.vertices[2].color, [0, 0, 0xFF, 0xFF]);
+
+        assert_eq!(mesh.textures.len(), 1);
+        let texture = &mesh.textures[0];
+        assert_eq!(texture.addr, 0x0600_0040);
+        assert_eq!(texture.format, ImageType::Rgba16);
+        assert_eq!((texture.width, texture.height), (32, 32));
+        assert_eq!(mesh.triangles[0].texture, Some(0));
+    }
+
+    #[test]
+    fn test_ci4_texture_palette() {
+        let data = ci4_triangle();
+        let mut segments = SegmentTable::default();
+        segments.set(6, 0, SegmentSource::OpenFile);
+        let memory = AddressSpace::new(&segments, &data);
+        let mesh = Interpreter::new(&memory, Microcode::F3dex2)
+            .run(0x0600_0100)
+            .unwrap();
+
+        assert_eq!(mesh.textures.len(), 1);
+        let texture = &mesh.textures[0];
+        assert_eq!(texture.addr, 0x0600_0040);
+        assert_eq!(texture.format, ImageType::Ci4);
+        assert_eq!((texture.width, texture.height), (16, 16));
+        assert_eq!(texture.tlut, Some(0x0600_00C0));
+
+        let tlut = texture.tlut_bytes(&memory).unwrap();
+        assert_eq!(tlut.len(), 32);
+        assert_eq!(&tlut[..4], &[0x00, 0x01, 0x00, 0x03]);
+    }
+
+    #[test]
+    fn test_loaded_texture_without_triangles() {
+        let mut data = ci4_triangle();
+        // Replace the triangle with gsDPPipeSync()
+        data[0x150..0x158].copy_from_slice(&[0xE7, 0, 0, 0, 0, 0, 0, 0]);
+        let mut segments = SegmentTable::default();
+        segments.set(6, 0, SegmentSource::OpenFile);
+        let memory = AddressSpace::new(&segments, &data);
+
+        let mesh = Interpreter::new(&memory, Microcode::F3dex2)
+            .run(0x0600_0100)
+            .unwrap();
+
+        assert!(mesh.triangles.is_empty());
+        assert_eq!(mesh.textures.len(), 1);
+        let texture = &mesh.textures[0];
+        assert_eq!(texture.addr, 0x0600_0040);
+        assert_eq!(texture.format, ImageType::Ci4);
+        assert_eq!((texture.width, texture.height), (16, 16));
+        assert_eq!(texture.tlut, Some(0x0600_00C0));
+    }
+
+    #[test]
+    fn test_call_and_return() {
+        let mut data = textured_triangle();
+        // 0x148: gsSPDisplayList(0x100); gsSPDisplayList(0x100); gsSPEndDisplayList()
+        push(&mut data, 0xDE00_0000, 0x0000_0100);
+        push(&mut data, 0xDE00_0000, 0x0000_0100);
+        push(&mut data, 0xDF00_0000, 0x0000_0000);
+        let mut segments = SegmentTable::default();
+        segments.set(6, 0, SegmentSource::OpenFile);
+        let memory = AddressSpace::new(&segments, &data);
+
+        let mesh = Interpreter::new(&memory, Microcode::F3dex2)
+            .run(0x0000_0148)
+            .unwrap();
+
+        assert_eq!(mesh.triangles.len(), 2);
+        assert_eq!(mesh.textures.len(), 1);
+        assert_eq!(mesh.trace.last().unwrap().depth, 0);
+    }
+
+    #[test]
+    fn test_unresolvable_start() {
+        let data = textured_triangle();
+        let segments = SegmentTable::default();
+        let memory = AddressSpace::new(&segments, &data);
+
+        assert!(Interpreter::new(&memory, Microcode::F3dex2)
+            .run(0x0600_0100)
+            .is_err());
+    }
+
+    #[test]
+    fn test_unloaded_vertex_warns() {
+        let mut data = vec![];
+        push(&mut data, 0x0500_0204, 0x0000_0000);
+        push(&mut data, 0xDF00_0000, 0x0000_0000);
+        let segments = SegmentTable::default();
+        let memory = AddressSpace::new(&segments, &data);
+
+        let mesh = Interpreter::new(&memory, Microcode::F3dex2).run(0).unwrap();
+        assert!(mesh.triangles.is_empty());
+        assert_eq!(mesh.warnings.len(), 1);
+    }
+}
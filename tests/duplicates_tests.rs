@@ -0,0 +1,68 @@
+use motex::display_list::{TextureDescriptor, TileDescriptor};
+use motex::duplicates::{file_regions, group_duplicates, TextureData};
+use pigment64::ImageType;
+
+#[cfg(test)]
+mod duplicates_tests {
+    use super::*;
+
+    fn texture(format: ImageType, raw: &[u8], rgba: &[u8]) -> TextureData {
+        TextureData {
+            format,
+            width: 1,
+            height: 1,
+            raw: raw.to_vec(),
+            rgba: rgba.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_exact_groups() {
+        let textures = [
+            texture(ImageType::Rgba16, &[0xF8, 0x01], &[255, 0, 0, 255]),
+            texture(ImageType::Rgba16, &[0x07, 0xC1], &[0, 255, 0, 255]),
+            texture(ImageType::Rgba16, &[0xF8, 0x01], &[255, 0, 0, 255]),
+            texture(ImageType::Ia16, &[0xF8, 0x01], &[248, 248, 248, 1]),
+        ];
+        assert_eq!(group_duplicates(&textures, None), [[0, 2]]);
+    }
+
+    #[test]
+    fn test_near_groups() {
+        let textures = [
+            texture(ImageType::Rgba32, &[250, 0, 0, 255], &[250, 0, 0, 255]),
+            texture(ImageType::Rgba16, &[0xF8, 0x01], &[255, 0, 0, 255]),
+            texture(ImageType::I8, &[0x80], &[128, 128, 128, 255]),
+        ];
+        assert!(group_duplicates(&textures, Some(2)).is_empty());
+        assert_eq!(group_duplicates(&textures, Some(8)), [[0, 1]]);
+    }
+
+    #[test]
+    fn test_near_groups_do_not_chain() {
+        // Each is within 8 of the next, but the first and last are 12 apart
+        let textures = [
+            texture(ImageType::I8, &[100], &[100, 100, 100, 255]),
+            texture(ImageType::I8, &[106], &[106, 106, 106, 255]),
+            texture(ImageType::I8, &[112], &[112, 112, 112, 255]),
+        ];
+        assert_eq!(group_duplicates(&textures, Some(8)), [[0, 1]]);
+    }
+
+    #[test]
+    fn test_file_regions() {
+        let template = TextureDescriptor {
+            addr: 0,
+            format: ImageType::Rgba16,
+            width: 4,
+            height: 2,
+            tlut: None,
+            tile: TileDescriptor::default(),
+        };
+        // 16 bytes per texture; the partial one at the end is left out
+        let regions = file_regions(40, &template, |offset| Some(offset as u32 | 0x0600_0000));
+        let addrs: Vec<u32> = regions.iter().map(|r| r.addr).collect();
+        assert_eq!(addrs, [0x0600_0000, 0x0600_0010]);
+        assert_eq!(regions[1].format, ImageType::Rgba16);
+    }
+}
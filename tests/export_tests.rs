@@ -0,0 +1,95 @@
+use motex::display_list::{Mesh, MeshTriangle, MeshVertex, TileDescriptor};
+use motex::export::{encode_png, mesh_to_glb, write_obj, DecodedTexture};
+
+mod common;
+use common::test_dir;
+
+/// A single triangle using texture 0, followed by an untextured triangle.
+fn two_triangles() -> (Mesh, Vec<DecodedTexture>) {
+    let vertex = |pos: [f32; 3]| MeshVertex {
+        pos,
+        uv: [pos[0], pos[1]],
+        color: [0xFF, 0x80, 0x00, 0xFF],
+        normal: None,
+    };
+    let mesh = Mesh {
+        vertices: vec![
+            vertex([0.0, 0.0, 0.0]),
+            vertex([1.0, 0.0, 0.0]),
+            vertex([0.0, 1.0, 0.0]),
+            vertex([0.0, 0.0, 1.0]),
+            vertex([1.0, 0.0, 1.0]),
+            vertex([0.0, 1.0, 1.0]),
+        ],
+        triangles: vec![
+            MeshTriangle {
+                indices: [0, 1, 2],
+                texture: Some(0),
+            },
+            MeshTriangle {
+                indices: [3, 4, 5],
+                texture: None,
+            },
+        ],
+        ..Default::default()
+    };
+    let textures = vec![DecodedTexture {
+        width: 2,
+        height: 2,
+        rgba: vec![0xFF; 16],
+        tile: TileDescriptor::default(),
+    }];
+    (mesh, textures)
+}
+
+#[cfg(test)]
+mod export_tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_png_signature() {
+        let png = encode_png(2, 2, &[0; 16]).unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+    }
+
+    #[test]
+    fn test_glb_layout() {
+        let (mesh, textures) = two_triangles();
+        let glb = mesh_to_glb(&mesh, &textures).unwrap();
+
+        assert_eq!(&glb[..4], b"glTF");
+        let total = u32::from_le_bytes(glb[8..12].try_into().unwrap()) as usize;
+        assert_eq!(total, glb.len());
+
+        let json_len = u32::from_le_bytes(glb[12..16].try_into().unwrap()) as usize;
+        assert_eq!(&glb[16..20], b"JSON");
+        assert_eq!(json_len % 4, 0);
+        let json = std::str::from_utf8(&glb[20..20 + json_len]).unwrap();
+        assert_eq!(json.matches("\"POSITION\"").count(), 2);
+        assert!(json.contains("\"mimeType\":\"image/png\""));
+
+        assert_eq!(&glb[24 + json_len..28 + json_len], b"BIN\0");
+
+        let gltf = gltf::Gltf::from_slice(&glb).unwrap();
+        assert_eq!(gltf.meshes().next().unwrap().primitives().len(), 2);
+        assert_eq!(gltf.textures().count(), 1);
+    }
+
+    #[test]
+    fn test_write_obj() {
+        let (mesh, textures) = two_triangles();
+        let dir = test_dir("write_obj");
+        let path = dir.join("model.obj");
+
+        write_obj(&path, &mesh, &textures).unwrap();
+
+        let obj = std::fs::read_to_string(&path).unwrap();
+        assert!(obj.starts_with("mtllib model.mtl"));
+        assert_eq!(obj.lines().filter(|l| l.starts_with("f ")).count(), 2);
+        assert!(obj.contains("usemtl tex0"));
+        assert!(dir.join("model.mtl").exists());
+        assert!(dir.join("model_tex0.png").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,16 @@
+use motex::hex_view::visible_start;
+
+#[cfg(test)]
+mod hex_view_tests {
+    use super::*;
+
+    #[test]
+    fn test_highlight_is_scrolled_into_view() {
+        // Rows start at the file position while the highlight is visible
+        assert_eq!(visible_start(0x105, 4, None), 0x100);
+        assert_eq!(visible_start(0x105, 4, Some(&(0x120..0x130))), 0x100);
+        // and move to the highlight when it is not
+        assert_eq!(visible_start(0x105, 4, Some(&(0x148..0x158))), 0x140);
+        assert_eq!(visible_start(0x105, 4, Some(&(0x20..0x30))), 0x20);
+    }
+}
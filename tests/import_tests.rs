@@ -0,0 +1,191 @@
+use motex::display_list::{Interpreter, Mesh, MeshTriangle, MeshVertex, Microcode};
+use motex::import::{build_display_list, load_obj, BuildOptions, ImportedModel, ImportedTexture};
+use motex::segments::{AddressSpace, SegmentSource, SegmentTable};
+use pigment64::ImageType;
+
+mod common;
+use common::test_dir;
+
+/// Builds a strip of `count` separate triangles.
+fn triangles(count: usize, texture: Option<usize>) -> Mesh {
+    let mut mesh = Mesh::default();
+    for i in 0..count {
+        let x = i as f32 * 10.0;
+        for (pos, uv) in [
+            ([x, 0.0, 0.0], [0.0, 0.0]),
+            ([x + 5.0, 0.0, 0.0], [1.0, 0.0]),
+            ([x, 5.0, 0.0], [0.0, 1.0]),
+        ] {
+            mesh.vertices.push(MeshVertex {
+                pos,
+                uv,
+                color: [0xFF; 4],
+                normal: None,
+            });
+        }
+        let base = i as u32 * 3;
+        mesh.triangles.push(MeshTriangle {
+            indices: [base, base + 1, base + 2],
+            texture,
+        });
+    }
+    mesh
+}
+
+/// Runs the generated display list and returns the resulting mesh.
+fn run(model: &ImportedModel, options: &BuildOptions) -> Mesh {
+    let generated = build_display_list(model, options).unwrap();
+    let mut segments = SegmentTable::default();
+    segments.set(options.segment, 0, SegmentSource::OpenFile);
+    let memory = AddressSpace::new(&segments, &generated.binary);
+
+    Interpreter::new(&memory, Microcode::F3dex2)
+        .run(generated.dl_address(options.segment))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod obj_tests {
+    use super::*;
+
+    #[test]
+    fn test_load_obj_quad() {
+        let dir = test_dir("load_obj_quad");
+        let path = dir.join("quad.obj");
+        std::fs::write(
+            &path,
+            "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvt 0 0\nvt 1 1\nf 1/1 2/2 3/2 4/1\n",
+        )
+        .unwrap();
+
+        let model = load_obj(&path).unwrap();
+        assert_eq!(model.mesh.triangles.len(), 2);
+        assert_eq!(model.mesh.vertices[2].pos, [1.0, 1.0, 0.0]);
+        // V is flipped to match the image row order.
+        assert_eq!(model.mesh.vertices[1].uv, [1.0, 0.0]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_obj_bad_index() {
+        let dir = test_dir("load_obj_bad_index");
+        let path = dir.join("bad.obj");
+        std::fs::write(&path, "v 0 0 0\nf 1 2 3\n").unwrap();
+
+        assert!(load_obj(&path).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod build_tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_untextured() {
+        let model = ImportedModel {
+            mesh: triangles(3, None),
+            textures: vec![],
+        };
+        let options = BuildOptions {
+            scale: 2.0,
+            ..Default::default()
+        };
+
+        let mesh = run(&model, &options);
+        assert!(mesh.warnings.is_empty(), "{:?}", mesh.warnings);
+        assert_eq!(mesh.triangles.len(), 3);
+        assert_eq!(mesh.vertices[4].pos, [30.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_batches_fit_vertex_cache() {
+        let model = ImportedModel {
+            mesh: triangles(40, None),
+            textures: vec![],
+        };
+
+        let mesh = run(&model, &BuildOptions::default());
+        assert!(mesh.warnings.is_empty(), "{:?}", mesh.warnings);
+        assert_eq!(mesh.triangles.len(), 40);
+    }
+
+    #[test]
+    fn test_round_trip_textured() {
+        let model = ImportedModel {
+            mesh: triangles(1, Some(0)),
+            textures: vec![ImportedTexture {
+                name: "checker".to_owned(),
+                width: 16,
+                height: 8,
+                rgba: vec![0xFF; 16 * 8 * 4],
+            }],
+        };
+
+        let mesh = run(&model, &BuildOptions::default());
+        assert_eq!(mesh.textures.len(), 1);
+        assert_eq!(mesh.textures[0].format, ImageType::Rgba16);
+        assert_eq!((mesh.textures[0].width, mesh.textures[0].height), (16, 8));
+        assert_eq!(mesh.textures[0].addr, 0x0600_0000);
+        assert_eq!(mesh.vertices[2].uv, [0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_rows_padded_to_tmem_words() {
+        // Texels numbered by their position, so rows can be told apart
+        let rgba: Vec<u8> = (0..6u8).flat_map(|i| [i * 8, 0, 0, 0xFF]).collect();
+        let model = ImportedModel {
+            mesh: triangles(1, Some(0)),
+            textures: vec![ImportedTexture {
+                name: "odd".to_owned(),
+                width: 3,
+                height: 2,
+                rgba,
+            }],
+        };
+
+        let generated = build_display_list(&model, &BuildOptions::default()).unwrap();
+        let texel =
+            |i: usize| u16::from_be_bytes([generated.binary[i * 2], generated.binary[i * 2 + 1]]);
+        // The second row starts after a padding texel
+        assert_eq!(texel(3), 0);
+        assert_eq!(texel(4), texel(0) + (24 >> 3 << 11));
+
+        let mesh = run(&model, &BuildOptions::default());
+        assert!(mesh.warnings.is_empty(), "{:?}", mesh.warnings);
+        assert_eq!((mesh.textures[0].width, mesh.textures[0].height), (3, 2));
+    }
+
+    #[test]
+    fn test_lighting_without_normals() {
+        let model = ImportedModel {
+            mesh: triangles(1, None),
+            textures: vec![],
+        };
+        let options = BuildOptions {
+            lighting: true,
+            ..Default::default()
+        };
+
+        let generated = build_display_list(&model, &options).unwrap();
+        // The first vertex's normal, rather than its white color
+        assert_eq!(generated.binary[12..16], [0, 0, 127, 0xFF]);
+    }
+
+    #[test]
+    fn test_texture_too_large() {
+        let model = ImportedModel {
+            mesh: triangles(1, Some(0)),
+            textures: vec![ImportedTexture {
+                name: "big".to_owned(),
+                width: 64,
+                height: 64,
+                rgba: vec![0; 64 * 64 * 4],
+            }],
+        };
+
+        assert!(build_display_list(&model, &BuildOptions::default()).is_err());
+    }
+}
@@ -0,0 +1,71 @@
+use std::{
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+use motex::jobs::{FinishedJob, Jobs};
+
+#[cfg(test)]
+mod jobs_tests {
+    use super::*;
+
+    /// Polls until a job finishes or a few seconds pass.
+    fn wait<T: Send + 'static>(jobs: &mut Jobs<T>) -> Vec<FinishedJob<T>> {
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(5) {
+            let finished = jobs.poll();
+            if !finished.is_empty() || jobs.is_empty() {
+                return finished;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        panic!("Job did not finish");
+    }
+
+    #[test]
+    fn test_result_is_delivered() {
+        let mut jobs = Jobs::default();
+        let id = jobs.spawn(None, "Sum", |job| {
+            job.set_progress(0.5);
+            Ok((1..=10).sum::<u32>())
+        });
+        assert!(jobs.is_running(id));
+
+        let finished = wait(&mut jobs);
+        assert_eq!(finished.len(), 1);
+        assert_eq!(finished[0].id, id);
+        assert_eq!(finished[0].name, "Sum");
+        assert_eq!(finished[0].result.as_ref().unwrap(), &55);
+        assert!(jobs.is_empty());
+    }
+
+    #[test]
+    fn test_errors_are_delivered() {
+        let mut jobs: Jobs<()> = Jobs::default();
+        jobs.spawn(None, "Fail", |_| anyhow::bail!("No luck"));
+        let finished = wait(&mut jobs);
+        assert_eq!(
+            finished[0].result.as_ref().unwrap_err().to_string(),
+            "No luck"
+        );
+    }
+
+    #[test]
+    fn test_cancelled_job_is_dropped() {
+        let (started_tx, started_rx) = mpsc::channel();
+        let mut jobs = Jobs::default();
+        let id = jobs.spawn(None, "Spin", move |job| {
+            started_tx.send(()).unwrap();
+            while job.tick(0.0) {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            job.check_cancelled()?;
+            Ok(())
+        });
+        started_rx.recv().unwrap();
+        jobs.cancel(id);
+
+        assert!(wait(&mut jobs).is_empty());
+        assert!(!jobs.is_running(id));
+    }
+}
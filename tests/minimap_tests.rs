@@ -0,0 +1,73 @@
+use motex::minimap::{
+    block_size, classify, classify_block, classify_with_progress, entropy, sample_block, ByteClass,
+    SAMPLE_SIZE,
+};
+
+#[cfg(test)]
+mod minimap_tests {
+    use super::*;
+
+    #[test]
+    fn test_entropy() {
+        assert_eq!(entropy(&[7; 64]), 0.0);
+        let all: Vec<u8> = (0..=255).collect();
+        assert!((entropy(&all) - 8.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_classify_block() {
+        assert_eq!(classify_block(&[0; 256]), ByteClass::Zero);
+        assert_eq!(classify_block(&[0x11; 256]), ByteClass::LowEntropy);
+
+        // lui / addiu / lw / sw with varying immediates
+        let code: Vec<u8> = (0..64u32)
+            .map(|i| [0x3C080000, 0x25080000, 0x8FA40000, 0xAFBF0000][i as usize % 4] | (i * 4))
+            .flat_map(|w| w.to_be_bytes())
+            .collect();
+        assert_eq!(classify_block(&code), ByteClass::Code);
+
+        // A simple LCG is random enough to look compressed
+        let mut state = 1u32;
+        let noise: Vec<u8> = (0..4096)
+            .map(|_| {
+                state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+                (state >> 24) as u8
+            })
+            .collect();
+        assert_eq!(classify_block(&noise), ByteClass::HighEntropy);
+    }
+
+    #[test]
+    fn test_block_size() {
+        assert_eq!(block_size(1000), 256);
+        assert_eq!(block_size(64 << 20), (64 << 20) / 4096);
+    }
+
+    #[test]
+    fn test_sample_block() {
+        let small = [1u8; 100];
+        assert_eq!(sample_block(&small).len(), 100);
+
+        let mut large = vec![0u8; 1 << 20];
+        *large.last_mut().unwrap() = 0xFF;
+        let sample = sample_block(&large);
+        assert_eq!(sample.len(), SAMPLE_SIZE);
+        // The last window reaches the end of the block
+        assert_eq!(sample.last(), Some(&0xFF));
+    }
+
+    #[test]
+    fn test_classify_with_progress() {
+        let data = vec![0u8; 256 * 200];
+        assert_eq!(classify(&data, 256).len(), 200);
+
+        let mut reports = vec![];
+        let classes = classify_with_progress(&data, 256, |fraction| {
+            reports.push(fraction);
+            reports.len() < 2
+        });
+        // Stopped at the second report, after one interval of blocks
+        assert_eq!(classes.len(), 64);
+        assert_eq!(reports, [0.0, 64.0 / 200.0]);
+    }
+}
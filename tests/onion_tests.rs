@@ -0,0 +1,41 @@
+use motex::onion::{blend, BlendMode, OverlayPixels};
+
+#[cfg(test)]
+mod blend_tests {
+    use super::*;
+
+    #[test]
+    fn test_difference() {
+        let mut base = vec![10, 20, 30, 255, 1, 2, 3, 255];
+        let overlay = [10, 20, 30, 255, 4, 2, 1, 255];
+        let differing = blend(&mut base, 2, &overlay, 2, BlendMode::Difference, 0.0);
+        assert_eq!(differing, 1);
+        assert_eq!(base, [0, 0, 0, 255, 3, 0, 2, 255]);
+    }
+
+    #[test]
+    fn test_onion() {
+        let mut base = vec![0, 0, 0, 255];
+        blend(&mut base, 1, &[200, 100, 50, 255], 1, BlendMode::Onion, 0.5);
+        assert_eq!(base, [100, 50, 25, 255]);
+    }
+
+    #[test]
+    fn test_smaller_overlay_counts_uncovered_pixels() {
+        let mut base = vec![0; 2 * 2 * 4];
+        let differing = blend(&mut base, 2, &[0; 4], 1, BlendMode::Difference, 0.0);
+        assert_eq!(differing, 3);
+    }
+}
+
+#[cfg(test)]
+mod overlay_pixels_tests {
+    use super::*;
+
+    #[test]
+    fn test_compared_by_decode() {
+        let pixels = OverlayPixels::new(1, vec![1, 2, 3, 4]);
+        assert_eq!(pixels.clone(), pixels);
+        assert_ne!(OverlayPixels::new(1, vec![1, 2, 3, 4]), pixels);
+    }
+}
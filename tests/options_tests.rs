@@ -30,10 +30,10 @@ mod appearance_tests {
     #[test]
     fn test_explicit_theme_settings() {
         let ctx = egui::Context::default();
-        let mut appearance = Appearance::default();
-
         // Test Dark theme
-        appearance.theme_choice = ThemeChoice::Dark;
+        let mut appearance = Appearance {
+            theme_choice: ThemeChoice::Dark,
+        };
         appearance.set_theme(&ctx);
         assert!(ctx.style().visuals.dark_mode);
 
@@ -46,10 +46,10 @@ mod appearance_tests {
     #[test]
     fn test_system_theme_behavior() {
         let ctx = egui::Context::default();
-        let mut appearance = Appearance::default();
-
         // Test System theme (which is the default)
-        appearance.theme_choice = ThemeChoice::System;
+        let appearance = Appearance {
+            theme_choice: ThemeChoice::System,
+        };
         appearance.set_theme(&ctx);
         // We can only verify that it's set to a valid mode
         assert!(matches!(ctx.style().visuals.dark_mode, true | false));
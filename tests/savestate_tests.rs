@@ -0,0 +1,103 @@
+use std::io::Write;
+
+use flate2::{write::GzEncoder, Compression};
+use motex::bin_handler::BinFile;
+use motex::savestate::{extract_rdram, is_savestate_path, Emulator, RDRAM_BASE};
+use std::path::Path;
+
+mod common;
+use common::test_dir;
+
+/// RDRAM contents as the N64 sees them.
+const RDRAM: [u8; 8] = [0x80, 0x01, 0x02, 0x03, 0xDE, 0xAD, 0xBE, 0xEF];
+
+/// RDRAM as the emulators store it, in little-endian words.
+const STORED: [u8; 8] = [0x03, 0x02, 0x01, 0x80, 0xEF, 0xBE, 0xAD, 0xDE];
+
+fn pj64_state() -> Vec<u8> {
+    let mut state = vec![];
+    state.extend_from_slice(&0x23D8_A6C8u32.to_le_bytes());
+    state.extend_from_slice(&(STORED.len() as u32).to_le_bytes());
+    state.resize(0x75C, 0);
+    state.extend_from_slice(&STORED);
+    state
+}
+
+#[cfg(test)]
+mod savestate_tests {
+    use super::*;
+
+    #[test]
+    fn test_project64() {
+        let (emulator, rdram) = extract_rdram(&pj64_state()).unwrap();
+        assert_eq!(emulator, Emulator::Project64);
+        assert_eq!(rdram, RDRAM);
+    }
+
+    #[test]
+    fn test_project64_zip() {
+        let mut buf = std::io::Cursor::new(vec![]);
+        let mut zip = zip::ZipWriter::new(&mut buf);
+        zip.start_file("game.pj", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(&pj64_state()).unwrap();
+        zip.finish().unwrap();
+
+        let (emulator, rdram) = extract_rdram(buf.get_ref()).unwrap();
+        assert_eq!(emulator, Emulator::Project64);
+        assert_eq!(rdram, RDRAM);
+    }
+
+    #[test]
+    fn test_mupen64plus_gzip() {
+        let mut state = b"M64+SAVE".to_vec();
+        state.resize(0x1B0, 0);
+        state.extend_from_slice(&STORED);
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(&state).unwrap();
+
+        let (emulator, rdram) = extract_rdram(&encoder.finish().unwrap()).unwrap();
+        assert_eq!(emulator, Emulator::Mupen64Plus);
+        assert_eq!(rdram, RDRAM);
+    }
+
+    #[test]
+    fn test_unknown_format() {
+        assert!(extract_rdram(b"Hello there!").is_err());
+    }
+
+    #[test]
+    fn test_savestate_paths() {
+        assert!(is_savestate_path(Path::new("game.pj")));
+        assert!(is_savestate_path(Path::new("GAME.PJ3")));
+        assert!(is_savestate_path(Path::new("game.st0")));
+        assert!(is_savestate_path(Path::new("game.pj.zip")));
+        assert!(!is_savestate_path(Path::new("game.z64")));
+    }
+
+    #[test]
+    fn test_other_zip_opens_as_raw_bytes() {
+        let mut buf = std::io::Cursor::new(vec![]);
+        let mut zip = zip::ZipWriter::new(&mut buf);
+        zip.start_file("readme.txt", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(b"Not a savestate").unwrap();
+        zip.finish().unwrap();
+        let bytes = buf.into_inner();
+
+        let dir = test_dir("other_zip");
+        let path = dir.join("archive.zip");
+        std::fs::write(&path, &bytes).unwrap();
+
+        let file = BinFile::open(&path).unwrap();
+        assert_eq!(file.data, bytes);
+        assert_eq!(file.base_address, 0);
+
+        std::fs::write(&path, pj64_state()).unwrap();
+        let file = BinFile::open(&path).unwrap();
+        assert_eq!(file.data, RDRAM);
+        assert_eq!(file.base_address, RDRAM_BASE);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,79 @@
+use motex::search::{
+    build_pattern, find_pattern, find_pattern_with_progress, parse_hex_pattern, Search, SearchKind,
+};
+
+#[cfg(test)]
+mod search_tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hex_pattern() {
+        assert_eq!(
+            parse_hex_pattern("80 37 ?? 40").unwrap(),
+            [Some(0x80), Some(0x37), None, Some(0x40)]
+        );
+        assert_eq!(
+            parse_hex_pattern("8037??40").unwrap(),
+            [Some(0x80), Some(0x37), None, Some(0x40)]
+        );
+        assert!(parse_hex_pattern("803").is_err());
+        assert!(parse_hex_pattern("zz").is_err());
+    }
+
+    #[test]
+    fn test_find_pattern() {
+        let data = [0x80, 0x37, 0x12, 0x40, 0x80, 0x37, 0x00, 0x40, 0x80, 0x37];
+        let pattern = parse_hex_pattern("80 37 ?? 40").unwrap();
+        assert_eq!(find_pattern(&data, &pattern, 0..data.len(), 1), [0, 4]);
+        assert_eq!(find_pattern(&data, &pattern, 1..data.len(), 1), [4]);
+        assert_eq!(find_pattern(&data, &pattern, 0..7, 1), [0]);
+    }
+
+    #[test]
+    fn test_aligned_values() {
+        let data = [0x00, 0x12, 0x34, 0x00, 0x12, 0x34, 0x56, 0x78];
+        let pattern = build_pattern(SearchKind::U16, "0x1234").unwrap();
+        assert_eq!(find_pattern(&data, &pattern, 0..data.len(), 2), [4]);
+        let pattern = build_pattern(SearchKind::U32, "-1").unwrap();
+        assert_eq!(pattern, [Some(0xFF); 4]);
+        assert!(build_pattern(SearchKind::U16, "0x12345").is_err());
+    }
+
+    #[test]
+    fn test_shift_jis() {
+        let pattern = build_pattern(SearchKind::ShiftJis, "マリオ").unwrap();
+        let bytes: Vec<u8> = pattern.into_iter().flatten().collect();
+        assert_eq!(bytes, [0x83, 0x7D, 0x83, 0x8A, 0x83, 0x49]);
+    }
+
+    #[test]
+    fn test_chunks_keep_straddling_hits() {
+        let mut data = vec![0u8; 64];
+        data[14..18].copy_from_slice(&[0xDE, 0xAD, 0xBE, 0xEF]);
+        data[40..44].copy_from_slice(&[0xDE, 0xAD, 0xBE, 0xEF]);
+        let pattern = parse_hex_pattern("DEADBEEF").unwrap();
+        let mut reports = 0;
+        let hits = find_pattern_with_progress(&data, &pattern, 0..64, 1, 16, |_| {
+            reports += 1;
+            true
+        });
+        assert_eq!(hits, [14, 40]);
+        assert_eq!(reports, 4);
+
+        // Stopping after the first chunk keeps its hits
+        let hits = find_pattern_with_progress(&data, &pattern, 0..64, 1, 16, |f| f == 0.0);
+        assert_eq!(hits, [14]);
+    }
+
+    #[test]
+    fn test_cycle_hits() {
+        let search = Search {
+            hits: vec![0x10, 0x20, 0x30],
+            ..Default::default()
+        };
+        assert_eq!(search.next_hit(0x10), Some(0x20));
+        assert_eq!(search.next_hit(0x30), Some(0x10));
+        assert_eq!(search.previous_hit(0x20), Some(0x10));
+        assert_eq!(search.previous_hit(0x10), Some(0x30));
+    }
+}
@@ -0,0 +1,88 @@
+use motex::segments::{parse_address, AddressSpace, SegmentSource, SegmentTable};
+use std::path::PathBuf;
+
+#[cfg(test)]
+mod segment_table_tests {
+    use super::*;
+
+    #[test]
+    fn test_default_maps_segment_zero_to_open_file() {
+        let table = SegmentTable::default();
+        let resolved = table.resolve(0x0000_1234).unwrap();
+        assert_eq!(resolved.segment, 0);
+        assert_eq!(resolved.source, &SegmentSource::OpenFile);
+        assert_eq!(resolved.offset, 0x1234);
+    }
+
+    #[test]
+    fn test_resolve_adds_segment_base() {
+        let mut table = SegmentTable::default();
+        table.set(6, 0x10_0000, SegmentSource::OpenFile);
+
+        let resolved = table.resolve(0x0600_0040).unwrap();
+        assert_eq!(resolved.segment, 6);
+        assert_eq!(resolved.offset, 0x10_0040);
+    }
+
+    #[test]
+    fn test_resolve_unmapped_segment() {
+        let table = SegmentTable::default();
+        assert!(table.resolve(0x0400_0000).is_none());
+    }
+
+    #[test]
+    fn test_resolve_rejects_upper_nibble() {
+        let table = SegmentTable::default();
+        assert!(table.resolve(0x8000_0000).is_none());
+    }
+
+    #[test]
+    fn test_resolve_external_source() {
+        let mut table = SegmentTable::default();
+        let path = PathBuf::from("tests/test_files/hello.txt");
+        table.set(2, 4, SegmentSource::External(path.clone()));
+
+        let resolved = table.resolve(0x0200_0002).unwrap();
+        assert_eq!(resolved.source, &SegmentSource::External(path));
+        assert_eq!(resolved.offset, 6);
+    }
+
+    #[test]
+    fn test_clear_unmaps_segment() {
+        let mut table = SegmentTable::default();
+        table.set(6, 0x100, SegmentSource::OpenFile);
+        table.clear(6);
+        assert!(table.get(6).is_none());
+    }
+
+    #[test]
+    fn test_to_segmented_prefers_closest_non_zero_segment() {
+        let mut table = SegmentTable::default();
+        table.set(5, 0x1000, SegmentSource::OpenFile);
+        table.set(6, 0x2000, SegmentSource::OpenFile);
+
+        assert_eq!(table.to_segmented(0x2010), Some(0x0600_0010));
+        assert_eq!(table.to_segmented(0x1010), Some(0x0500_0010));
+        assert_eq!(table.to_segmented(0x0010), Some(0x0000_0010));
+    }
+
+    #[test]
+    fn test_parse_address() {
+        assert_eq!(parse_address("0x06001000"), Some(0x0600_1000));
+        assert_eq!(parse_address(" 80 "), Some(0x80));
+        assert_eq!(parse_address("0XFF"), Some(0xFF));
+        assert_eq!(parse_address("zz"), None);
+    }
+
+    #[test]
+    fn test_address_space_base_address() {
+        let table = SegmentTable::default();
+        let data = [1, 2, 3, 4];
+        let memory = AddressSpace::new(&table, &data).with_base_address(0x8000_0000);
+
+        assert_eq!(memory.slice(0x8000_0002), Some(&data[2..]));
+        assert_eq!(memory.slice(0x8000_0004), None);
+        // Segmented addresses still resolve through the table
+        assert_eq!(memory.slice(0x0000_0001), Some(&data[1..]));
+    }
+}
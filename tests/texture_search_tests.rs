@@ -0,0 +1,77 @@
+use motex::texture_search::{
+    encode_texture, find_ci_pattern, find_texture, find_texture_with_progress, PROGRESS_INTERVAL,
+};
+use pigment64::ImageType;
+
+#[cfg(test)]
+mod texture_search_tests {
+    use super::*;
+
+    /// A 4x4 image with four colors.
+    fn image() -> Vec<u8> {
+        let colors = [[255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 255], [0; 4]];
+        (0..16).flat_map(|i| colors[(i * 7 / 3) % 4]).collect()
+    }
+
+    #[test]
+    fn test_find_exact() {
+        let rgba = image();
+        let encoded = encode_texture(4, 4, &rgba, ImageType::Rgba16).unwrap();
+        let mut data = vec![0x11; 100];
+        data[40..40 + encoded.len()].copy_from_slice(&encoded);
+
+        let hits = find_texture(&data, 4, 4, &rgba, 0).unwrap();
+        assert!(hits
+            .iter()
+            .any(|h| h.offset == 40 && h.format == ImageType::Rgba16));
+    }
+
+    #[test]
+    fn test_find_tolerant() {
+        let rgba = image();
+        let mut encoded = encode_texture(4, 4, &rgba, ImageType::Rgba16).unwrap();
+        // Nudge the blue channel of the first pixel by one step
+        encoded[1] ^= 0x02;
+        let mut data = vec![0x11; 100];
+        data[8..8 + encoded.len()].copy_from_slice(&encoded);
+
+        let exact = find_texture(&data, 4, 4, &rgba, 0).unwrap();
+        assert!(!exact.iter().any(|h| h.format == ImageType::Rgba16));
+        let tolerant = find_texture(&data, 4, 4, &rgba, 1).unwrap();
+        assert!(tolerant
+            .iter()
+            .any(|h| h.offset == 8 && h.format == ImageType::Rgba16));
+    }
+
+    #[test]
+    fn test_ci_pattern_ignores_palette_order() {
+        let labels = [0, 1, 1, 2, 0, 3, 3, 3];
+        // Only 7 5 5 A 7 9 9 9 at offset 1 follows the pattern
+        let data = [0xFF, 0x75, 0x5A, 0x79, 0x99, 0x00];
+        assert_eq!(
+            find_ci_pattern(&data, &labels, ImageType::Ci4, |_| true),
+            [1]
+        );
+    }
+
+    #[test]
+    fn test_cancel_within_format() {
+        let rgba = image();
+        let encoded = encode_texture(4, 4, &rgba, ImageType::Rgba16).unwrap();
+        let mut data = vec![0x11; 2 * PROGRESS_INTERVAL];
+        let offset = PROGRESS_INTERVAL + 8;
+        data[offset..offset + encoded.len()].copy_from_slice(&encoded);
+
+        let mut reports = vec![];
+        let hits = find_texture_with_progress(&data, 4, 4, &rgba, 0, |fraction| {
+            reports.push(fraction);
+            reports.len() < 3
+        })
+        .unwrap();
+        // Stopped at the second checkpoint inside the first format
+        assert_eq!(reports.len(), 3);
+        assert_eq!(reports[1], 0.0);
+        assert!(reports[2] > 0.0);
+        assert!(hits.is_empty());
+    }
+}
@@ -0,0 +1,130 @@
+use motex::display_list::TileDescriptor;
+use motex::texview::{gather_rows, raw_pixel, tile_texture, wrap_coord, ByteSwap};
+use pigment64::ImageType;
+
+#[cfg(test)]
+mod wrap_tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(wrap_coord(9, 8, 0, 3, 0), 1);
+        assert_eq!(wrap_coord(-1, 8, 0, 3, 0), 7);
+    }
+
+    #[test]
+    fn test_mirror() {
+        let mirror = TileDescriptor::MIRROR;
+        assert_eq!(wrap_coord(8, 8, mirror, 3, 0), 7);
+        assert_eq!(wrap_coord(15, 8, mirror, 3, 0), 0);
+        assert_eq!(wrap_coord(-1, 8, mirror, 3, 0), 0);
+    }
+
+    #[test]
+    fn test_clamp() {
+        let clamp = TileDescriptor::CLAMP;
+        assert_eq!(wrap_coord(20, 8, clamp, 3, 0), 7);
+        assert_eq!(wrap_coord(-5, 8, clamp, 3, 0), 0);
+        // No mask means no wrapping
+        assert_eq!(wrap_coord(20, 8, 0, 0, 0), 7);
+    }
+
+    #[test]
+    fn test_shift() {
+        // Shift 1 halves the coordinate, 15 doubles it
+        assert_eq!(wrap_coord(6, 8, 0, 3, 1), 3);
+        assert_eq!(wrap_coord(3, 8, 0, 3, 15), 6);
+    }
+
+    #[test]
+    fn test_tile_texture_centers_original() {
+        // A 2x1 texture with a red and a green pixel
+        let rgba = [0xFF, 0, 0, 0xFF, 0, 0xFF, 0, 0xFF];
+        let tile = TileDescriptor {
+            masks: 1,
+            ..Default::default()
+        };
+
+        let out = tile_texture(&rgba, 2, 1, &tile, 6, 3);
+        assert_eq!(out.len(), 6 * 3 * 4);
+        // Row 1 is the original row; every row is clamped to it
+        let row: Vec<u8> = out[..6 * 4].iter().step_by(4).copied().collect();
+        assert_eq!(row, [0xFF, 0, 0xFF, 0, 0xFF, 0]);
+        assert_eq!(&out[..24], &out[24..48]);
+    }
+}
+
+#[cfg(test)]
+mod stride_tests {
+    use super::*;
+
+    #[test]
+    fn test_gather_rows() {
+        // 2x2 RGBA16 image in a 3-pixel pitch
+        let data: Vec<u8> = (0..12).collect();
+        let rows = gather_rows(&data, ImageType::Rgba16, 2, 2, 3);
+        assert_eq!(rows, [0, 1, 2, 3, 6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn test_gather_rows_truncated() {
+        let data: Vec<u8> = (0..8).collect();
+        let rows = gather_rows(&data, ImageType::Rgba16, 2, 3, 3);
+        assert_eq!(rows, [0, 1, 2, 3, 6, 7]);
+    }
+}
+
+#[cfg(test)]
+mod swap_tests {
+    use super::*;
+
+    #[test]
+    fn test_swap32() {
+        let data: Vec<u8> = (0..8).collect();
+        assert_eq!(ByteSwap::Swap32.read(&data, 0, 8), [3, 2, 1, 0, 7, 6, 5, 4]);
+    }
+
+    #[test]
+    fn test_swap_keeps_file_alignment() {
+        let data: Vec<u8> = (0..8).collect();
+        assert_eq!(ByteSwap::Swap16.read(&data, 1, 4), [0, 3, 2, 5]);
+        assert_eq!(ByteSwap::Swap64.read(&data, 2, 3), [5, 4, 3]);
+    }
+
+    #[test]
+    fn test_swap_past_end() {
+        let data: Vec<u8> = (0..6).collect();
+        assert_eq!(ByteSwap::Swap32.read(&data, 0, 8), [3, 2, 1, 0]);
+        assert!(ByteSwap::Swap32.read(&data, 4, 4).is_empty());
+        assert!(ByteSwap::Swap32.read(&data, 8, 4).is_empty());
+    }
+}
+
+#[cfg(test)]
+mod raw_pixel_tests {
+    use super::*;
+
+    #[test]
+    fn test_rgba16_pixel() {
+        let data = [0, 0, 0x12, 0x34, 0xF8, 0x3F, 0x56, 0x78];
+        let raw = raw_pixel(&data, 2, ImageType::Rgba16, 2, ByteSwap::None, 1, 0).unwrap();
+        assert_eq!((raw.offset, raw.bits, raw.value), (4, 16, 0xF83F));
+        assert!(raw_pixel(&data, 2, ImageType::Rgba16, 2, ByteSwap::None, 0, 2).is_none());
+    }
+
+    #[test]
+    fn test_4bpp_pixel_nibbles() {
+        let data = [0xAB, 0xCD];
+        let high = raw_pixel(&data, 0, ImageType::Ci4, 2, ByteSwap::None, 0, 1).unwrap();
+        assert_eq!((high.offset, high.bit, high.value), (1, 4, 0xC));
+        let low = raw_pixel(&data, 0, ImageType::Ci4, 2, ByteSwap::None, 1, 1).unwrap();
+        assert_eq!((low.offset, low.bit, low.value), (1, 0, 0xD));
+    }
+
+    #[test]
+    fn test_swapped_pixel_offset() {
+        let data = [0x3F, 0xF8, 0x00, 0x00];
+        let raw = raw_pixel(&data, 0, ImageType::Rgba16, 2, ByteSwap::Swap16, 0, 0).unwrap();
+        assert_eq!((raw.offset, raw.value), (1, 0xF83F));
+    }
+}
@@ -0,0 +1,25 @@
+use motex::vtx_view::read_vertices;
+
+#[cfg(test)]
+mod read_vertices_tests {
+    use super::*;
+
+    #[test]
+    fn test_read_vertices_at_offset() {
+        let mut data = vec![0xFF; 4];
+        for i in 0..3i16 {
+            data.extend_from_slice(&i.to_be_bytes());
+            data.extend_from_slice(&[0; 14]);
+        }
+
+        let vertices = read_vertices(&data, 4, 16);
+        assert_eq!(vertices.len(), 3);
+        assert_eq!(vertices[2].pos[0], 2);
+    }
+
+    #[test]
+    fn test_read_vertices_past_end() {
+        let data = [0; 32];
+        assert!(read_vertices(&data, 64, 4).is_empty());
+    }
+}
@@ -0,0 +1,58 @@
+use std::time::{Duration, SystemTime};
+
+use motex::watcher::{file_stamp, FileWatcher};
+
+mod common;
+use common::test_dir;
+
+#[cfg(test)]
+mod watcher_tests {
+    use super::*;
+
+    #[test]
+    fn test_change_must_settle() {
+        let dir = test_dir("change_must_settle");
+        let path = dir.join("watched.bin");
+        std::fs::write(&path, [0u8; 4]).unwrap();
+
+        let mut watcher = FileWatcher::default();
+        watcher.watch(&path);
+        let loaded = file_stamp(&path).unwrap();
+        assert!(!watcher.check(Some(loaded)));
+
+        let changed = (loaded.0 + Duration::from_secs(1), 8);
+        assert!(!watcher.check(Some(changed)));
+        assert!(watcher.check(Some(changed)));
+        // Reported once
+        assert!(!watcher.check(Some(changed)));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_missing_file_is_not_a_change() {
+        let mut watcher = FileWatcher::default();
+        watcher.check(Some((SystemTime::UNIX_EPOCH, 4)));
+        assert!(!watcher.check(None));
+        assert!(!watcher.check(None));
+    }
+
+    #[test]
+    fn test_active_only_while_watching() {
+        let dir = test_dir("active_only_while_watching");
+        let path = dir.join("watched.bin");
+        std::fs::write(&path, [0u8; 4]).unwrap();
+
+        let mut watcher = FileWatcher::default();
+        assert!(!watcher.is_active());
+        watcher.watch(&path);
+        assert!(watcher.is_active());
+        watcher.paused = true;
+        assert!(!watcher.is_active());
+        watcher.paused = false;
+        watcher.unwatch();
+        assert!(!watcher.is_active());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}